@@ -0,0 +1,485 @@
+//! Zero-copy parsing of MIDI data held in memory. Chunk, text, sysex and unknown payloads borrow
+//! from the input buffer instead of being copied, and can be upgraded to the owned types once
+//! needed
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    chunk::{
+        chunk_types::{HEADER_CHUNK, TRACK_DATA_CHUNK},
+        header::{HeaderChunk, InvalidFormat},
+        track::{
+            event::MidiEvent, meta::MetaEvent, sysex::SysexEvent, Event, MTrkEvent, TrackChunk,
+            TrackError,
+        },
+        ChunkParseError, ErrorLocation, UnknownChunk,
+    },
+    reader::{split_chunk, Counted},
+    Chunk, Midi, MidiSanitizerError,
+};
+
+/// Error from parsing a [`MidiRef`]
+#[derive(Debug)]
+pub enum MidiRefError {
+    /// A chunk failed to parse
+    Parse(ChunkParseError),
+    /// The chunks don't form a valid MIDI file
+    Sanitize(MidiSanitizerError),
+}
+
+impl core::error::Error for MidiRefError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            Self::Sanitize(e) => Some(e),
+        }
+    }
+}
+impl core::fmt::Display for MidiRefError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Parse(e) => write![f, "Failed to parse MIDI data: {e}"],
+            Self::Sanitize(e) => write![f, "Invalid MIDI structure: {e}"],
+        }
+    }
+}
+impl From<ChunkParseError> for MidiRefError {
+    fn from(f: ChunkParseError) -> Self {
+        Self::Parse(f)
+    }
+}
+impl From<MidiSanitizerError> for MidiRefError {
+    fn from(f: MidiSanitizerError) -> Self {
+        Self::Sanitize(f)
+    }
+}
+
+/// A MIDI file borrowed from a byte buffer, the borrowed counterpart of [`Midi`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiRef<'a> {
+    /// Header chunk
+    pub header: HeaderChunk,
+    /// All tracks, in order
+    pub tracks: Vec<TrackRef<'a>>,
+    /// Chunks of unknown types, in order
+    pub unknown_chunks: Vec<UnknownChunkRef<'a>>,
+}
+
+/// A chunk of an unrecognized type borrowed from a byte buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnknownChunkRef<'a> {
+    /// Number of track chunks that came before this chunk in the file
+    pub position: usize,
+    /// 4 character ASCII chunk type
+    pub chunk_type: [char; 4],
+    /// The chunk's raw payload
+    pub data: &'a [u8],
+}
+
+/// A track chunk borrowed from a byte buffer, the borrowed counterpart of [`TrackChunk`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackRef<'a> {
+    /// All events in the track
+    events: Vec<MTrkEventRef<'a>>,
+}
+
+/// An MTrk event borrowed from a byte buffer, the borrowed counterpart of [`MTrkEvent`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MTrkEventRef<'a> {
+    /// Ticks to wait after the previous event before this one occurs
+    delta_time: u32,
+    /// The event itself
+    event: EventRef<'a>,
+}
+
+/// An event borrowed from a byte buffer, the borrowed counterpart of [`Event`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventRef<'a> {
+    /// A MIDI channel event, which is small enough to be decoded in place
+    MidiEvent(MidiEvent),
+    /// A system exclusive event
+    SysexEvent(SysexRef<'a>),
+    /// A meta event
+    MetaEvent(MetaRef<'a>),
+}
+
+/// A system exclusive event borrowed from a byte buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SysexRef<'a> {
+    /// `F0` or `F7` prefix byte
+    prefix: u8,
+    /// Raw payload after the length
+    data: &'a [u8],
+    /// Whether a divided message was waiting on continuation packets before this event
+    divided: bool,
+}
+
+/// A meta event borrowed from a byte buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetaRef<'a> {
+    /// Meta event tag
+    tag: u8,
+    /// Raw payload after the length
+    data: &'a [u8],
+}
+
+impl<'a> MidiRef<'a> {
+    /// Parses a MIDI file from a byte buffer without copying its payloads. Chunks of unknown
+    /// types are kept in [`MidiRef::unknown_chunks`]
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, MidiRefError> {
+        let mut header = None;
+        let mut tracks = vec![];
+        let mut unknown_chunks = vec![];
+        let mut pos = 0;
+
+        while pos < bytes.len() {
+            let index = tracks.len() + unknown_chunks.len() + usize::from(header.is_some());
+            let location = ErrorLocation {
+                offset: pos,
+                chunk: index,
+                ..Default::default()
+            };
+
+            let (chunk, data) = split_chunk(&bytes[pos..])
+                .map_err(|source| ChunkParseError::Stream { location, source })?;
+            let payload_offset = pos + 8;
+            pos = payload_offset + data.len();
+
+            match chunk.chunk_type {
+                HEADER_CHUNK => {
+                    if header.is_some() {
+                        return Err(MidiSanitizerError::TooManyHeaders.into());
+                    }
+                    let parsed =
+                        parse_header(chunk, data).map_err(|e| e.in_chunk(index, payload_offset))?;
+                    header = Some(parsed);
+                }
+                _ if header.is_none() => return Err(MidiSanitizerError::NoStartHeader.into()),
+                TRACK_DATA_CHUNK => tracks
+                    .push(TrackRef::parse(data).map_err(|e| e.in_chunk(index, payload_offset))?),
+                chunk_type => unknown_chunks.push(UnknownChunkRef {
+                    position: tracks.len(),
+                    chunk_type,
+                    data,
+                }),
+            }
+        }
+
+        Ok(Self {
+            header: header.ok_or(MidiSanitizerError::NoChunks)?,
+            tracks,
+            unknown_chunks,
+        })
+    }
+
+    /// Copies the borrowed data into an owned [`Midi`]
+    pub fn to_midi(&self) -> Midi {
+        Midi {
+            header: self.header,
+            tracks: self.tracks.iter().map(TrackRef::to_track_chunk).collect(),
+            unknown_chunks: self
+                .unknown_chunks
+                .iter()
+                .map(|chunk| UnknownChunk {
+                    position: chunk.position,
+                    chunk_type: chunk.chunk_type,
+                    data: chunk.data.to_vec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&MidiRef<'_>> for Midi {
+    fn from(value: &MidiRef<'_>) -> Self {
+        value.to_midi()
+    }
+}
+
+impl<'a> TrackRef<'a> {
+    /// Parses a track chunk's payload without copying it
+    pub fn parse(data: &'a [u8]) -> Result<Self, ChunkParseError> {
+        let mut cursor = Cursor { data, pos: 0 };
+        let mut events = vec![];
+        let mut running_status = None;
+        let mut divided = false;
+
+        while cursor.pos < data.len() {
+            match cursor.read_event(&mut running_status, &mut divided) {
+                Ok(event) => events.push(event),
+                Err(source) => {
+                    // The offending byte is the last one read, unless the data ran out
+                    let offset = match source {
+                        TrackError::OutOfSpace => data.len(),
+                        _ => cursor.pos.saturating_sub(1),
+                    };
+                    let location = ErrorLocation {
+                        offset,
+                        chunk: 0,
+                        event: Some(events.len()),
+                        byte: data.get(offset).copied(),
+                    };
+
+                    return Err(ChunkParseError::TrackParseError { location, source });
+                }
+            }
+        }
+
+        Ok(Self { events })
+    }
+
+    /// Gets the track's events
+    pub fn events(&self) -> &[MTrkEventRef<'a>] {
+        &self.events
+    }
+
+    /// Copies the borrowed data into an owned [`TrackChunk`]
+    pub fn to_track_chunk(&self) -> TrackChunk {
+        TrackChunk::new(
+            self.events
+                .iter()
+                .map(MTrkEventRef::to_mtrk_event)
+                .collect(),
+        )
+    }
+}
+
+impl<'a> MTrkEventRef<'a> {
+    /// Gets the number of ticks to wait after the previous event before this one occurs
+    pub fn delta_time(&self) -> u32 {
+        self.delta_time
+    }
+
+    /// Gets the event
+    pub fn event(&self) -> EventRef<'a> {
+        self.event
+    }
+
+    /// Copies the borrowed data into an owned [`MTrkEvent`]
+    pub fn to_mtrk_event(&self) -> MTrkEvent {
+        MTrkEvent::from_parts(self.delta_time, self.event.to_event())
+    }
+}
+
+impl EventRef<'_> {
+    /// Copies the borrowed data into an owned [`Event`]
+    pub fn to_event(&self) -> Event {
+        match self {
+            Self::MidiEvent(event) => Event::MidiEvent(*event),
+            Self::SysexEvent(sysex) => Event::SysexEvent(sysex.to_sysex_event()),
+            Self::MetaEvent(meta) => Event::MetaEvent(meta.to_meta_event()),
+        }
+    }
+}
+
+impl<'a> SysexRef<'a> {
+    /// Gets the `F0` or `F7` prefix byte
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Gets the raw payload, including a trailing `F7` if present
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Copies the borrowed data into an owned [`SysexEvent`]
+    pub fn to_sysex_event(&self) -> SysexEvent {
+        // UNWRAP Safety: The payload is checked with `SysexEvent::check_data` while parsing
+        SysexEvent::try_from_data(self.prefix, self.data.to_vec(), &mut self.divided.clone())
+            .unwrap()
+    }
+}
+
+impl<'a> MetaRef<'a> {
+    /// Gets the meta event tag
+    pub fn tag(&self) -> u8 {
+        self.tag
+    }
+
+    /// Gets the raw payload
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Gets the payload as text for the text, copyright, track name, instrument name, lyric and
+    /// marker events
+    pub fn text(&self) -> Option<&'a str> {
+        match self.tag {
+            0x01..=0x06 => core::str::from_utf8(self.data).ok(),
+            _ => None,
+        }
+    }
+
+    /// Copies the borrowed data into an owned [`MetaEvent`]
+    pub fn to_meta_event(&self) -> MetaEvent {
+        // UNWRAP Safety: The payload is checked with `MetaEvent::check_data` while parsing
+        MetaEvent::try_from_data(self.tag, self.data.to_vec()).unwrap()
+    }
+}
+
+/// Parses a header chunk's payload
+fn parse_header(chunk: Chunk, data: &[u8]) -> Result<HeaderChunk, ChunkParseError> {
+    if chunk.len() != 6 {
+        return Err(InvalidFormat.into());
+    }
+
+    let format = u16::from_be_bytes([data[0], data[1]]);
+    let ntrks = u16::from_be_bytes([data[2], data[3]]);
+    let division = u16::from_be_bytes([data[4], data[5]]);
+
+    Ok(HeaderChunk::try_from((format, ntrks, division))?)
+}
+
+/// Reads events from a track payload by position, without allocating
+struct Cursor<'a> {
+    /// The track payload
+    data: &'a [u8],
+    /// Number of bytes consumed so far
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Reads a single byte
+    fn byte(&mut self) -> Result<u8, TrackError> {
+        let byte = *self.data.get(self.pos).ok_or(TrackError::OutOfSpace)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Reads a number of bytes as a slice of the payload
+    fn take(&mut self, n: usize) -> Result<&'a [u8], TrackError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos.saturating_add(n))
+            .ok_or(TrackError::OutOfSpace)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    /// Reads a variable length quantity
+    fn vlq(&mut self) -> Result<u32, TrackError> {
+        let mut bytes = Counted::new(self.data[self.pos..].iter().copied());
+        let value = MTrkEvent::try_get_delta_time(&mut bytes).ok_or(TrackError::OutOfSpace)?;
+        self.pos += bytes.count;
+        Ok(value)
+    }
+
+    /// Reads a length prefixed payload
+    fn payload(&mut self) -> Result<&'a [u8], TrackError> {
+        let len = self.vlq()?;
+        self.take(len as usize)
+    }
+
+    /// Reads a single MTrk event, following the same rules as [`Event::try_from_state`]
+    fn read_event(
+        &mut self,
+        running_status: &mut Option<u8>,
+        divided: &mut bool,
+    ) -> Result<MTrkEventRef<'a>, TrackError> {
+        let delta_time = self.vlq()?;
+        let status = self.byte()?;
+
+        let event = match status {
+            0x80..=0xEF => {
+                *running_status = Some(status);
+                let len = MidiEvent::data_len(status).unwrap_or(0);
+                EventRef::MidiEvent(MidiEvent::from_data(status, self.take(len)?)?)
+            }
+
+            0x00..=0x7F => {
+                let status = running_status.ok_or(TrackError::MissingRunningStatus)?;
+                // The byte just read is the first data byte
+                self.pos -= 1;
+                let len = MidiEvent::data_len(status).unwrap_or(0);
+                EventRef::MidiEvent(MidiEvent::from_data(status, self.take(len)?)?)
+            }
+
+            0xF0 | 0xF7 => {
+                *running_status = None;
+                let before = *divided;
+                let data = self.payload()?;
+                SysexEvent::check_data(status, data, divided)?;
+
+                EventRef::SysexEvent(SysexRef {
+                    prefix: status,
+                    data,
+                    divided: before,
+                })
+            }
+
+            0xFF => {
+                *running_status = None;
+                let tag = self.byte()?;
+                let data = self.payload()?;
+                MetaEvent::check_data(tag, data)?;
+
+                EventRef::MetaEvent(MetaRef { tag, data })
+            }
+
+            _ => return Err(TrackError::InvalidFormat),
+        };
+
+        Ok(MTrkEventRef { delta_time, event })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::bytes_from_tracks, RawMidi};
+
+    use super::{EventRef, MidiRef, TrackRef};
+
+    #[test]
+    fn borrowed_parse_matches_owned_parse() {
+        for path in ["test/test.mid", "test/run.mid"] {
+            let bytes = std::fs::read(path).expect("Read test file");
+
+            let owned = RawMidi::try_from_midi_stream(bytes.clone().into_iter())
+                .expect("Parse owned")
+                .check_into_midi()
+                .expect("Sanitize owned");
+            let borrowed = MidiRef::parse(&bytes).expect("Parse borrowed");
+
+            assert_eq!(borrowed.to_midi(), owned)
+        }
+    }
+
+    #[test]
+    fn payloads_borrow_from_the_input() {
+        let bytes = bytes_from_tracks(
+            96,
+            &[&[
+                0x00, 0xFF, 0x03, 0x04, b'L', b'e', b'a', b'd', // Track name
+                0x00, 0xF0, 0x03, 0x43, 0x12, 0xF7, // Sysex
+                0x00, 0x90, 0x3C, 0x40, 0x10, 0x3C, 0x00, // Running status
+                0x00, 0xFF, 0x2F, 0x00,
+            ]],
+        );
+        let midi = MidiRef::parse(&bytes).expect("Parse borrowed");
+        let events = midi.tracks[0].events();
+
+        let EventRef::MetaEvent(name) = events[0].event() else {
+            panic!("Expected a meta event");
+        };
+        assert_eq!(name.text(), Some("Lead"));
+        assert!(bytes.as_ptr_range().contains(&name.data().as_ptr()));
+
+        let EventRef::SysexEvent(sysex) = events[1].event() else {
+            panic!("Expected a sysex event");
+        };
+        assert_eq!(sysex.data(), &[0x43, 0x12, 0xF7]);
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn borrowed_errors_match_owned_errors() {
+        let track: &[u8] = &[0x00, 0xC0, 0x05, 0x00, 0xFF, 0x01, 0x00, 0x00, 0x06];
+
+        let owned = crate::chunk::track::TrackChunk::try_from(track.to_vec()).unwrap_err();
+        let borrowed = TrackRef::parse(track).unwrap_err();
+
+        assert_eq!(borrowed.location(), owned.location());
+        assert_eq!(borrowed.to_string(), owned.to_string())
+    }
+}
@@ -1,11 +1,16 @@
 //! Chunk Definitions for parsed types and type headers
 
+use alloc::vec::Vec;
+
+use crate::io::{self, Write};
+
 use header::{HeaderChunk, InvalidFormat};
 use track::TrackChunk;
 
 use crate::{
     chunk::chunk_types::{HEADER_CHUNK, TRACK_DATA_CHUNK},
-    writer::MidiWriteable,
+    reader::StreamError,
+    writer::{write_chunk, MidiWriteable, WriteMidi, WriteOptions},
     Chunk,
 };
 
@@ -25,12 +30,166 @@ pub enum ParsedChunk {
     Header(HeaderChunk),
     /// A track chunk,
     Track(TrackChunk),
+    /// A chunk of an unrecognized type, kept as-is so it can be written back unchanged
+    Unknown {
+        /// 4 character ASCII chunk type
+        chunk_type: [char; 4],
+        /// The chunk's raw payload
+        data: Vec<u8>,
+    },
+}
+
+/// A chunk of an unrecognized type kept by a sanitized [`crate::Midi`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnknownChunk {
+    /// Number of track chunks that came before this chunk in the file, used to write it back in
+    /// the same place
+    pub position: usize,
+    /// 4 character ASCII chunk type
+    pub chunk_type: [char; 4],
+    /// The chunk's raw payload
+    pub data: Vec<u8>,
+}
+
+impl From<UnknownChunk> for ParsedChunk {
+    fn from(value: UnknownChunk) -> Self {
+        ParsedChunk::Unknown {
+            chunk_type: value.chunk_type,
+            data: value.data,
+        }
+    }
 }
 
 impl MidiWriteable for ParsedChunk {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let val: (Chunk, Vec<u8>) = self.into();
-        val.to_midi_bytes()
+        self.to_midi_vec(WriteOptions::default())
+    }
+
+    fn to_midi_bytes_with_options(self, options: WriteOptions) -> Vec<u8> {
+        self.to_midi_vec(options)
+    }
+}
+
+/// Writes the whole chunk, including its chunk header
+impl WriteMidi for ParsedChunk {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        match self {
+            ParsedChunk::Header(header) => write_chunk(writer, HEADER_CHUNK, header, options),
+            ParsedChunk::Track(track) => write_chunk(writer, TRACK_DATA_CHUNK, track, options),
+            ParsedChunk::Unknown { chunk_type, data } => {
+                write_chunk(writer, *chunk_type, data.as_slice(), options)
+            }
+        }
+    }
+}
+
+/// Writes the whole chunk, including its chunk header
+impl WriteMidi for UnknownChunk {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        write_chunk(writer, self.chunk_type, self.data.as_slice(), options)
+    }
+}
+
+impl ParsedChunk {
+    /// Converts the parsed chunk back into its raw chunk and payload, serializing the payload
+    /// with the provided [`WriteOptions`]
+    pub fn into_chunk_data_pair(self, options: WriteOptions) -> (Chunk, Vec<u8>) {
+        match self {
+            ParsedChunk::Header(header) => {
+                let bytes = header.to_midi_bytes();
+                let chunk = Chunk {
+                    chunk_type: HEADER_CHUNK,
+                    length: bytes.len() as u32,
+                };
+
+                (chunk, bytes)
+            }
+            ParsedChunk::Track(track) => {
+                let bytes = track.to_midi_bytes_with_options(options);
+
+                let chunk = Chunk {
+                    chunk_type: TRACK_DATA_CHUNK,
+                    length: bytes.len() as u32,
+                };
+                (chunk, bytes)
+            }
+            ParsedChunk::Unknown { chunk_type, data } => {
+                let chunk = Chunk {
+                    chunk_type,
+                    length: data.len() as u32,
+                };
+                (chunk, data)
+            }
+        }
+    }
+}
+
+/// Error for a value that doesn't fit in the MIDI field it was given for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange {
+    /// Name of the field the value was given for
+    pub field: &'static str,
+    /// The rejected value
+    pub value: i64,
+}
+
+impl OutOfRange {
+    /// Checks that a value is within an inclusive range, returning it if so
+    pub(crate) fn check<T>(field: &'static str, value: T, min: T, max: T) -> Result<T, Self>
+    where
+        T: PartialOrd + Into<i64> + Copy,
+    {
+        if value < min || value > max {
+            Err(Self {
+                field,
+                value: value.into(),
+            })
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl core::error::Error for OutOfRange {}
+impl core::fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "Value {} is out of range for {}", self.value, self.field]
+    }
+}
+
+/// Where in a MIDI file a parse error was found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErrorLocation {
+    /// Byte offset of the offending byte, or of where the data ran out. Absolute from the start
+    /// of the file when parsing a whole stream, otherwise relative to the chunk's payload
+    pub offset: usize,
+    /// Index of the chunk being parsed
+    pub chunk: usize,
+    /// Index of the event being parsed within a track chunk
+    pub event: Option<usize>,
+    /// The offending byte, if the error wasn't caused by running out of data
+    pub byte: Option<u8>,
+}
+
+impl core::fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "offset {} in chunk {}", self.offset, self.chunk]?;
+        if let Some(event) = self.event {
+            write![f, ", event {event}"]?;
+        }
+        if let Some(byte) = self.byte {
+            write![f, " (byte {byte:#04X})"]?;
+        }
+        Ok(())
     }
 }
 
@@ -38,63 +197,96 @@ impl MidiWriteable for ParsedChunk {
 #[derive(Debug)]
 pub enum ChunkParseError {
     /// Invalid format in parsing a header
-    InvalidFormat(InvalidFormat),
-    /// Type tag is not registered
-    UnknownType,
+    InvalidFormat {
+        /// Where the header was found
+        location: ErrorLocation,
+        /// The underlying error
+        source: InvalidFormat,
+    },
     /// Random todo during debugging
     Todo(&'static str),
     /// Error parsing track
-    TrackParseError(track::TrackError),
+    TrackParseError {
+        /// Where the failing event was found
+        location: ErrorLocation,
+        /// The underlying error
+        source: track::TrackError,
+    },
+    /// The stream couldn't be read
+    Stream {
+        /// Where the unreadable chunk starts
+        location: ErrorLocation,
+        /// The underlying error
+        source: StreamError,
+    },
 }
 
-impl core::error::Error for ChunkParseError {}
+impl ChunkParseError {
+    /// Gets where the error was found, if known
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        match self {
+            Self::InvalidFormat { location, .. }
+            | Self::TrackParseError { location, .. }
+            | Self::Stream { location, .. } => Some(location),
+            Self::Todo(_) => None,
+        }
+    }
+
+    /// Places an error found in a single chunk's payload within the whole file, given the chunk's
+    /// index and the absolute offset its payload starts at
+    pub(crate) fn in_chunk(mut self, chunk: usize, payload_offset: usize) -> Self {
+        if let Self::InvalidFormat { location, .. } | Self::TrackParseError { location, .. } =
+            &mut self
+        {
+            location.chunk = chunk;
+            location.offset += payload_offset;
+        }
+        self
+    }
+}
+
+impl core::error::Error for ChunkParseError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidFormat { source, .. } => Some(source),
+            Self::TrackParseError { source, .. } => Some(source),
+            Self::Stream { source, .. } => Some(source),
+            Self::Todo(_) => None,
+        }
+    }
+}
 impl core::fmt::Display for ChunkParseError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Self::InvalidFormat(_) => write![f, "Invalid Format Specified"],
-            Self::UnknownType => write![f, "Unknown Chunk Type"],
+            Self::InvalidFormat { location, source } => write![f, "{source} at {location}"],
             Self::Todo(s) => write![f, "Development TODO: {s}"],
-            Self::TrackParseError(_) => write![f, "Track parsing error"],
+            Self::TrackParseError { location, source } => {
+                write![f, "Track parsing error at {location}: {source}"]
+            }
+            Self::Stream { location, source } => write![f, "{source} at {location}"],
         }
     }
 }
 impl From<InvalidFormat> for ChunkParseError {
     fn from(f: InvalidFormat) -> Self {
-        Self::InvalidFormat(f)
+        Self::InvalidFormat {
+            location: ErrorLocation::default(),
+            source: f,
+        }
     }
 }
 impl From<track::TrackError> for ChunkParseError {
     fn from(f: track::TrackError) -> Self {
-        Self::TrackParseError(f)
+        Self::TrackParseError {
+            location: ErrorLocation::default(),
+            source: f,
+        }
     }
 }
 
 impl From<ParsedChunk> for (Chunk, Vec<u8>) {
     fn from(value: ParsedChunk) -> Self {
-        match value {
-            ParsedChunk::Header(header) => {
-                let bytes = header.to_midi_bytes();
-                let chunk = Chunk {
-                    chunk_type: HEADER_CHUNK,
-                    length: bytes.len() as u32,
-                };
-
-                (chunk, bytes)
-            }
-            ParsedChunk::Track(track) => {
-                let mut bytes = vec![];
-
-                for mtrk_event in track.mtrk_events {
-                    bytes.extend(mtrk_event.to_midi_bytes().iter());
-                }
-
-                let chunk = Chunk {
-                    chunk_type: TRACK_DATA_CHUNK,
-                    length: bytes.len() as u32,
-                };
-                (chunk, bytes)
-            }
-        }
+        value.into_chunk_data_pair(WriteOptions::default())
     }
 }
 
@@ -112,7 +304,7 @@ impl TryFrom<(Chunk, Vec<u8>)> for ParsedChunk {
                     let parsed = HeaderChunk::try_from((format, ntrk, division))?;
                     Ok(ParsedChunk::Header(parsed))
                 } else {
-                    Err(ChunkParseError::InvalidFormat(InvalidFormat))
+                    Err(InvalidFormat.into())
                 }
             }
 
@@ -121,7 +313,37 @@ impl TryFrom<(Chunk, Vec<u8>)> for ParsedChunk {
                 Ok(ParsedChunk::Track(parsed))
             }
 
-            _ => Err(ChunkParseError::UnknownType),
+            chunk_type => Ok(ParsedChunk::Unknown { chunk_type, data }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{writer::MidiWriteable, Chunk};
+
+    use super::ParsedChunk;
+
+    #[test]
+    fn unknown_chunks_round_trip() {
+        let data = vec![0x01, 0x02, 0x03];
+        let chunk = Chunk {
+            chunk_type: ['X', 'F', 'I', 'H'],
+            length: 3,
+        };
+
+        let parsed = ParsedChunk::try_from((chunk, data.clone())).expect("Parse unknown chunk");
+        assert_eq!(
+            parsed,
+            ParsedChunk::Unknown {
+                chunk_type: ['X', 'F', 'I', 'H'],
+                data
+            }
+        );
+
+        assert_eq!(
+            parsed.to_midi_bytes(),
+            vec![b'X', b'F', b'I', b'H', 0, 0, 0, 3, 0x01, 0x02, 0x03]
+        )
+    }
+}
@@ -1,9 +1,16 @@
 //! Header Chunk Enum and Struct Definitions
 
+use alloc::{vec, vec::Vec};
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::writer::MidiWriteable;
+use crate::io::{self, Write};
+
+use crate::{
+    chunk::OutOfRange,
+    writer::{MidiWriteable, WriteMidi, WriteOptions},
+};
 
 /// Header chunk data, including format, ntrks and division as 3 16 bit unsigned integers
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -17,6 +24,106 @@ pub struct HeaderChunk {
     division: Division,
 }
 
+impl HeaderChunk {
+    /// Creates a builder for a header chunk
+    pub fn builder() -> HeaderChunkBuilder {
+        HeaderChunkBuilder::default()
+    }
+
+    /// Creates a new header chunk, failing if a metrical division doesn't fit in 15 bits or a
+    /// format 0 header declares more than one track
+    pub fn new(format: Format, ntrks: u16, division: Division) -> Result<Self, OutOfRange> {
+        Self::builder()
+            .format(format)
+            .ntrks(ntrks)
+            .division(division)
+            .build()
+    }
+
+    /// Creates a header chunk without validating it, for headers derived from parsed files
+    pub(crate) fn from_parts(format: Format, ntrks: u16, division: Division) -> Self {
+        Self {
+            format,
+            ntrks,
+            division,
+        }
+    }
+
+    /// Gets the header's MIDI format
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Gets the number of tracks the header declares
+    pub fn ntrks(&self) -> u16 {
+        self.ntrks
+    }
+
+    /// Gets the header's division, the meaning of delta times in the file
+    pub fn division(&self) -> Division {
+        self.division
+    }
+}
+
+/// Builder for a [`HeaderChunk`]. Defaults to format 1 with no tracks and 480 ticks per quarter
+/// note
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeaderChunkBuilder {
+    /// The MIDI format
+    format: Format,
+    /// Number of tracks
+    ntrks: u16,
+    /// Time signature/division
+    division: Division,
+}
+
+impl Default for HeaderChunkBuilder {
+    fn default() -> Self {
+        Self {
+            format: Format::One,
+            ntrks: 0,
+            division: Division::Metrical(480),
+        }
+    }
+}
+
+impl HeaderChunkBuilder {
+    /// Sets the MIDI format
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the number of tracks
+    pub fn ntrks(mut self, ntrks: u16) -> Self {
+        self.ntrks = ntrks;
+        self
+    }
+
+    /// Sets the division
+    pub fn division(mut self, division: Division) -> Self {
+        self.division = division;
+        self
+    }
+
+    /// Builds the header chunk, failing if a metrical division doesn't fit in 15 bits or a
+    /// format 0 header declares more than one track
+    pub fn build(self) -> Result<HeaderChunk, OutOfRange> {
+        if let Division::Metrical(ticks) = self.division {
+            OutOfRange::check("ticks per quarter note", ticks, 1, 0x7FFF)?;
+        }
+        if self.format == Format::Zero {
+            OutOfRange::check("format 0 track count", self.ntrks, 0, 1)?;
+        }
+
+        Ok(HeaderChunk::from_parts(
+            self.format,
+            self.ntrks,
+            self.division,
+        ))
+    }
+}
+
 impl MidiWriteable for HeaderChunk {
     fn to_midi_bytes(self) -> Vec<u8> {
         let mut bytes = self.format.to_midi_bytes();
@@ -30,6 +137,17 @@ impl MidiWriteable for HeaderChunk {
     }
 }
 
+/// Writes the header's payload, without the chunk header
+impl WriteMidi for HeaderChunk {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        _options: WriteOptions,
+    ) -> io::Result<()> {
+        writer.write_all(&self.to_midi_bytes())
+    }
+}
+
 impl TryFrom<(u16, u16, u16)> for HeaderChunk {
     type Error = InvalidFormat;
     fn try_from(value: (u16, u16, u16)) -> Result<Self, Self::Error> {
@@ -113,6 +231,54 @@ pub struct SmpteTicks {
     tpf: u8,
 }
 
+impl SmpteTicks {
+    /// Creates a new time-code-based division from a frame rate of 24, 25, 29 (30 drop frame) or
+    /// 30 frames per second and a number of ticks per frame
+    pub fn new(frames_per_second: u8, ticks_per_frame: u8) -> Result<Self, OutOfRange> {
+        if ![24, 25, 29, 30].contains(&frames_per_second) {
+            return Err(OutOfRange {
+                field: "SMPTE frames per second",
+                value: frames_per_second.into(),
+            });
+        }
+
+        let smpte = (frames_per_second as i8).wrapping_neg().to_be_bytes()[0];
+        let bits = u16::from_be_bytes([smpte, ticks_per_frame]);
+
+        // Decode the same way as a parsed header so both compare equal
+        match Division::from(bits) {
+            Division::TimeCodeBased(ticks) => Ok(ticks),
+            Division::Metrical(_) => unreachable!("Negative SMPTE formats always set bit 15"),
+        }
+    }
+
+    /// Gets the SMPTE frame rate as stored in the header, the negated frames per second. -29
+    /// denotes 30 drop frame (29.97 frames per second)
+    pub fn smpte_format(&self) -> i8 {
+        // The format is stored as a 7 bit value, restore bit 15 of the division to read the whole
+        // byte as a negative two's complement number
+        (self.smpte.to_be_bytes()[0] | 0x80) as i8
+    }
+
+    /// Gets the number of ticks per frame
+    pub fn ticks_per_frame(&self) -> u8 {
+        self.tpf
+    }
+
+    /// Gets the number of ticks per second as a fraction of `(numerator, denominator)`
+    pub fn ticks_per_second(&self) -> (u64, u64) {
+        let fps = self.smpte_format().unsigned_abs() as u64;
+        let tpf = self.tpf as u64;
+
+        if fps == 29 {
+            // 30 drop frame runs at 30000 / 1001 frames per second
+            (30_000 * tpf, 1001)
+        } else {
+            (fps * tpf, 1)
+        }
+    }
+}
+
 impl MidiWriteable for SmpteTicks {
     fn to_midi_bytes(self) -> Vec<u8> {
         const MASK: u8 = 0x80;
@@ -199,7 +365,7 @@ mod tests {
 
         assert_eq!(test, expected);
 
-        let test: Division = (0x8bFFu16).into();
+        let test: Division = (0x8BFFu16).into();
         let expected = Division::TimeCodeBased(SmpteTicks {
             smpte: -117,
             tpf: 255,
@@ -208,15 +374,71 @@ mod tests {
         assert_eq!(test, expected)
     }
 
+    #[test]
+    fn smpte_ticks_constructor_matches_parsed_division() {
+        for (fps, bits) in [(24, 0xE828u16), (25, 0xE728), (29, 0xE350), (30, 0xE204)] {
+            let tpf = bits as u8;
+            let parsed: Division = bits.into();
+
+            assert_eq!(
+                SmpteTicks::new(fps, tpf).map(Division::TimeCodeBased),
+                Ok(parsed)
+            );
+            assert_eq!(
+                SmpteTicks::new(fps, tpf).unwrap().smpte_format(),
+                -(fps as i8)
+            );
+        }
+
+        assert!(SmpteTicks::new(60, 4).is_err())
+    }
+
+    #[test]
+    fn header_chunk_builder_validates() {
+        let header = HeaderChunk::builder()
+            .format(Format::Zero)
+            .ntrks(1)
+            .division(Division::Metrical(96))
+            .build();
+        assert_eq!(
+            header,
+            Ok(HeaderChunk {
+                format: Format::Zero,
+                ntrks: 1,
+                division: Division::Metrical(96)
+            })
+        );
+
+        assert!(HeaderChunk::builder()
+            .format(Format::Zero)
+            .ntrks(2)
+            .build()
+            .is_err());
+        assert!(HeaderChunk::builder()
+            .division(Division::Metrical(0x8000))
+            .build()
+            .is_err());
+
+        assert_eq!(
+            HeaderChunk::new(Format::Zero, 1, Division::Metrical(96)),
+            header
+        );
+        assert!(HeaderChunk::new(Format::One, 2, Division::Metrical(0x8000)).is_err());
+    }
+
     #[test]
     fn header_chunk_reads_properly() {
-        let mut data = "test/run.mid"
+        let mut data = std::fs::read("test/run.mid")
+            .expect("Read test file")
             .get_midi_bytes()
             .expect("Get `run.midi` file and stream bytes");
 
-        let (header, payload) = data.read_chunk_data_pair().expect("Get chunk and data");
+        let (header, payload) = data
+            .read_chunk_data_pair()
+            .expect("Read chunk")
+            .expect("Get chunk and data");
 
-        let header: Chunk = header.into();
+        let header: Chunk = header;
         assert_eq!(header, HEADER_CHUNK_RAW);
 
         // Now we try reading the next 6 bytes as [u16; 3]
@@ -1,19 +1,26 @@
 //! Track chunk data enums and structs
 
-use std::string::FromUtf8Error;
+use alloc::{string::FromUtf8Error, vec, vec::Vec};
 
 use event::{IteratorWrapper, MidiEvent, UnsupportedStatusCode};
 use meta::MetaEvent;
 use sysex::SysexEvent;
+use value::U7;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::writer::MidiWriteable;
+use crate::{
+    chunk::{ChunkParseError, ErrorLocation, OutOfRange},
+    io::{self, Write},
+    reader::Counted,
+    writer::{write_vlq, MidiWriteable, WriteMidi, WriteOptions},
+};
 
 pub mod event;
 pub mod meta;
 pub mod sysex;
+pub mod value;
 
 /// Error types from parsing a track
 #[derive(Debug, Clone, PartialEq)]
@@ -24,8 +31,12 @@ pub enum TrackError {
     OutOfSpace,
     /// Invalid chunk format
     InvalidFormat,
+    /// A data byte was found where a status byte was expected and no running status is in effect
+    MissingRunningStatus,
     /// MIDI Channel Event status code is invalid
     UnsupportedStatusCode(UnsupportedStatusCode),
+    /// A MIDI Channel Event data byte has its high bit set
+    InvalidDataByte(u8),
     /// Meta Event is in an invalid format
     InvalidMetaEventData,
     /// Invalid start tag for sysex message
@@ -36,15 +47,29 @@ pub enum TrackError {
     UtfParseError(FromUtf8Error),
 }
 
-impl core::error::Error for TrackError {}
+impl core::error::Error for TrackError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::UnsupportedStatusCode(e) => Some(e),
+            Self::UtfParseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 impl core::fmt::Display for TrackError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::EOF => write![f, "Reached end of line at end of parsing"],
             Self::OutOfSpace => write![f, "Reached end of chunk before done parsing"],
             Self::InvalidFormat => write![f, "Invalid Track Format"],
+            Self::MissingRunningStatus => {
+                write![f, "Data byte found with no running status in effect"]
+            }
             Self::UnsupportedStatusCode(e) => {
-                write![f, "Invalid Status Code for MIDI Channel Event {e}"]
+                write![f, "Invalid Status Code for MIDI Channel Event: {e}"]
+            }
+            Self::InvalidDataByte(byte) => {
+                write![f, "MIDI Channel Event data byte {byte:#04X} is above 0x7F"]
             }
             Self::InvalidMetaEventData => write![f, "Meta Event data is in an invalid format"],
             Self::InvalidSysExMessage => write![f, "Invalid SysEx Message Start"],
@@ -70,7 +95,7 @@ impl From<FromUtf8Error> for TrackError {
 }
 
 /// A track chunk, containing one or more MTrk events
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TrackChunk {
     /// All associated track events to this chunk
@@ -78,23 +103,301 @@ pub struct TrackChunk {
 }
 
 impl TryFrom<Vec<u8>> for TrackChunk {
-    type Error = TrackError;
+    type Error = ChunkParseError;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        let mut value = value.into_iter();
-        let mut mtrk_events = vec![];
+        let mtrk_events = TrackEvents::new(&value).collect::<Result<_, _>>()?;
+        Ok(Self { mtrk_events })
+    }
+}
 
-        loop {
-            match MTrkEvent::try_from(IteratorWrapper(&mut value)) {
-                Ok(new_track) => mtrk_events.push(new_track),
-                Err(TrackError::EOF) => break,
-                Err(e) => return Err(e),
+/// Lazily decodes the MTrk events of a track chunk's payload one at a time. Errors are yielded as
+/// items, after which the iterator ends
+#[derive(Debug, Clone)]
+pub struct TrackEvents<'a> {
+    /// The track payload
+    data: &'a [u8],
+    /// Number of payload bytes consumed so far
+    pos: usize,
+    /// Running status and divided sysex state
+    state: TrackParseState,
+    /// Number of events yielded so far
+    index: usize,
+    /// Index of the chunk within its file, used in error locations
+    chunk: usize,
+    /// Absolute offset of the payload within its file, used in error locations
+    base: usize,
+    /// Set once the payload is exhausted or an error was yielded
+    done: bool,
+}
+
+impl<'a> TrackEvents<'a> {
+    /// Creates an iterator over a track chunk's payload. Error offsets are relative to the
+    /// payload
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::in_chunk(data, 0, 0)
+    }
+
+    /// Creates an iterator over a track chunk's payload found in a file, so that error locations
+    /// carry the chunk's index and absolute offsets
+    pub(crate) fn in_chunk(data: &'a [u8], chunk: usize, base: usize) -> Self {
+        Self {
+            data,
+            pos: 0,
+            state: TrackParseState::default(),
+            index: 0,
+            chunk,
+            base,
+            done: false,
+        }
+    }
+
+    /// Gets the number of payload bytes decoded so far
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+impl Iterator for TrackEvents<'_> {
+    type Item = Result<MTrkEvent, ChunkParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut bytes = Counted::new(self.data[self.pos..].iter().copied());
+        let result = MTrkEvent::try_from_state(&mut bytes, &mut self.state);
+        let consumed = self.pos + bytes.count;
+
+        match result {
+            Ok(event) => {
+                self.pos = consumed;
+                self.index += 1;
+                Some(Ok(event))
+            }
+            Err(TrackError::EOF) => {
+                self.done = true;
+                None
+            }
+            Err(source) => {
+                self.done = true;
+
+                // The offending byte is the last one read, unless the data ran out
+                let offset = match source {
+                    TrackError::OutOfSpace => consumed,
+                    _ => consumed.saturating_sub(1),
+                };
+                let location = ErrorLocation {
+                    offset: self.base + offset,
+                    chunk: self.chunk,
+                    event: Some(self.index),
+                    byte: self.data.get(offset).copied(),
+                };
+
+                Some(Err(ChunkParseError::TrackParseError { location, source }))
             }
         }
+    }
+}
+
+impl core::iter::FusedIterator for TrackEvents<'_> {}
+
+impl TrackChunk {
+    /// Creates a track from a list of events
+    pub fn new(mtrk_events: Vec<MTrkEvent>) -> Self {
+        Self { mtrk_events }
+    }
+
+    /// Creates a builder for a track chunk
+    pub fn builder() -> TrackChunkBuilder {
+        TrackChunkBuilder::default()
+    }
+
+    /// Gets the track's events
+    pub fn events(&self) -> &[MTrkEvent] {
+        &self.mtrk_events
+    }
+
+    /// Gets the track's events mutably. Each event's delta time is still checked by
+    /// [`MTrkEvent::new`], but the track as a whole isn't validated the way
+    /// [`TrackChunkBuilder::build`] does it, so keeping an `EndOfTrack` last is up to the caller
+    pub fn events_mut(&mut self) -> &mut Vec<MTrkEvent> {
+        &mut self.mtrk_events
+    }
+
+    /// Consumes the track, returning its events
+    pub fn into_events(self) -> Vec<MTrkEvent> {
+        self.mtrk_events
+    }
+
+    /// Iterates over the track's events along with the absolute tick each occurs at, accumulated
+    /// from the delta times of every preceding event
+    pub fn absolute_events(&self) -> AbsoluteEvents<'_> {
+        AbsoluteEvents {
+            events: self.mtrk_events.iter(),
+            tick: 0,
+        }
+    }
+
+    /// Builds a track from events paired with the absolute tick they occur at. Events are
+    /// stably sorted by tick and their delta times are computed from the gaps between them. Fails
+    /// if two consecutive events are more than [`MTrkEvent::MAX_DELTA_TIME`] ticks apart
+    pub fn from_absolute_events<EVENTS>(events: EVENTS) -> Result<Self, OutOfRange>
+    where
+        EVENTS: IntoIterator<Item = (u64, Event)>,
+    {
+        let mut events: Vec<_> = events.into_iter().collect();
+        events.sort_by_key(|(tick, _)| *tick);
+
+        let mut previous = 0;
+        let mtrk_events = events
+            .into_iter()
+            .map(|(tick, event)| {
+                let gap = tick - previous;
+                previous = tick;
+
+                let delta_time = u32::try_from(gap).map_err(|_| OutOfRange {
+                    field: "delta time",
+                    value: gap.min(i64::MAX as u64) as i64,
+                })?;
+                MTrkEvent::new(delta_time, event)
+            })
+            .collect::<Result<_, _>>()?;
 
         Ok(Self { mtrk_events })
     }
 }
 
+/// Builder for a [`TrackChunk`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackChunkBuilder {
+    /// Events added so far
+    mtrk_events: Vec<MTrkEvent>,
+    /// Absolute tick of the last added event
+    tick: u64,
+    /// First invalid delta time that was added
+    error: Option<OutOfRange>,
+}
+
+impl TrackChunkBuilder {
+    /// Adds an event that occurs `delta_time` ticks after the previous one
+    pub fn event(mut self, delta_time: u32, event: impl Into<Event>) -> Self {
+        match MTrkEvent::new(delta_time, event.into()) {
+            Ok(mtrk_event) => {
+                self.tick += delta_time as u64;
+                self.mtrk_events.push(mtrk_event);
+            }
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+
+        self
+    }
+
+    /// Adds an event that occurs at an absolute tick, which must not be earlier than the
+    /// previously added event
+    pub fn event_at(mut self, tick: u64, event: impl Into<Event>) -> Self {
+        match tick.checked_sub(self.tick).map(u32::try_from) {
+            Some(Ok(delta_time)) => self.event(delta_time, event),
+            _ => {
+                self.error.get_or_insert(OutOfRange {
+                    field: "absolute tick",
+                    value: tick.min(i64::MAX as u64) as i64,
+                });
+                self
+            }
+        }
+    }
+
+    /// Builds the track, appending an `EndOfTrack` event if the last event isn't one. Fails if
+    /// any event was given a delta time that can't be encoded
+    pub fn build(self) -> Result<TrackChunk, OutOfRange> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        let mut mtrk_events = self.mtrk_events;
+        if !matches!(
+            mtrk_events.last(),
+            Some(MTrkEvent {
+                event: Event::MetaEvent(MetaEvent::EndOfTrack),
+                ..
+            })
+        ) {
+            mtrk_events.push(MTrkEvent {
+                delta_time: 0,
+                event: Event::MetaEvent(MetaEvent::EndOfTrack),
+            });
+        }
+
+        Ok(TrackChunk { mtrk_events })
+    }
+}
+
+/// Iterator over a track's events paired with their absolute tick, created by
+/// [`TrackChunk::absolute_events`]
+#[derive(Debug, Clone)]
+pub struct AbsoluteEvents<'a> {
+    /// Remaining events in the track
+    events: core::slice::Iter<'a, MTrkEvent>,
+    /// Absolute tick of the last yielded event
+    tick: u64,
+}
+
+impl<'a> Iterator for AbsoluteEvents<'a> {
+    type Item = (u64, &'a Event);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mtrk_event = self.events.next()?;
+        self.tick += mtrk_event.delta_time as u64;
+
+        Some((self.tick, &mtrk_event.event))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+impl ExactSizeIterator for AbsoluteEvents<'_> {}
+
+impl MidiWriteable for TrackChunk {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        self.to_midi_vec(WriteOptions::default())
+    }
+
+    fn to_midi_bytes_with_options(self, options: WriteOptions) -> Vec<u8> {
+        self.to_midi_vec(options)
+    }
+}
+
+/// Writes the track's payload, without the chunk header
+impl WriteMidi for TrackChunk {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        let mut running_status = None;
+
+        for mtrk_event in &self.mtrk_events {
+            mtrk_event.write_with_running_status(writer, &mut running_status, options)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// State carried from one event to the next while parsing a track
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrackParseState {
+    /// Status byte of the last MIDI channel event, reused by events that omit their status byte
+    pub running_status: Option<u8>,
+    /// True while a divided system exclusive message is waiting on continuation packets
+    pub divided_sysex: bool,
+}
+
 /// A MIDI Event with a DeltaTime and an attached Event
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -108,12 +411,18 @@ pub struct MTrkEvent {
 
 impl MidiWriteable for MTrkEvent {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let mut bytes = MTrkEvent::to_midi_vlq(self.delta_time);
-        let event_bytes = self.event.to_midi_bytes();
-
-        bytes.extend(event_bytes.iter());
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
 
-        bytes
+/// Writes the event on its own, as if no running status were in effect
+impl WriteMidi for MTrkEvent {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        self.write_with_running_status(writer, &mut None, options)
     }
 }
 
@@ -123,50 +432,131 @@ where
 {
     type Error = TrackError;
     fn try_from(value: IteratorWrapper<&mut ITER>) -> Result<Self, Self::Error> {
-        let value = value.0;
+        MTrkEvent::try_from_state(value.0, &mut TrackParseState::default())
+    }
+}
+
+impl MTrkEvent {
+    /// Largest delta time that can be encoded as a variable length quantity
+    pub const MAX_DELTA_TIME: u32 = 0x0FFF_FFFF;
+
+    /// Creates a new MTrk event, failing if the delta time is larger than
+    /// [`MTrkEvent::MAX_DELTA_TIME`]
+    pub fn new(delta_time: u32, event: impl Into<Event>) -> Result<Self, OutOfRange> {
+        Ok(Self {
+            delta_time: OutOfRange::check("delta time", delta_time, 0, Self::MAX_DELTA_TIME)?,
+            event: event.into(),
+        })
+    }
+
+    /// Creates an MTrk event without validating the delta time
+    pub(crate) fn from_parts(delta_time: u32, event: Event) -> Self {
+        Self { delta_time, event }
+    }
 
+    /// Gets the number of ticks to wait after the previous event before this one occurs
+    pub fn delta_time(&self) -> u32 {
+        self.delta_time
+    }
+
+    /// Gets the event
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// Consumes the MTrk event, returning its event
+    pub fn into_event(self) -> Event {
+        self.event
+    }
+
+    /// Serializes the event, omitting its status byte if it matches `running_status` and the
+    /// options enable running status. `running_status` is updated to reflect the written event
+    pub fn to_midi_bytes_with_running_status(
+        self,
+        running_status: &mut Option<u8>,
+        options: WriteOptions,
+    ) -> Vec<u8> {
+        let mut bytes = vec![];
+        // Writing to a `Vec` never fails
+        let _ = self.write_with_running_status(&mut bytes, running_status, options);
+        bytes
+    }
+
+    /// Writes the event into a writer, see [`MTrkEvent::to_midi_bytes_with_running_status`]
+    pub fn write_with_running_status<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        running_status: &mut Option<u8>,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        write_vlq(writer, self.delta_time)?;
+
+        match &self.event {
+            Event::MidiEvent(event) if options.running_status => {
+                let event = match *event {
+                    MidiEvent::NoteOff(channel, note) if options.note_off_as_note_on => {
+                        MidiEvent::NoteOn(channel, note.with_velocity(U7::MIN))
+                    }
+                    event => event,
+                };
+
+                let status = event.get_status_channel_combo();
+                if *running_status == Some(status) {
+                    let (data, len) = event.data_bytes();
+                    writer.write_all(&data[..len])?;
+                } else {
+                    event.write_midi(writer, options)?;
+                }
+
+                *running_status = Some(status);
+            }
+            Event::MidiEvent(event) => {
+                *running_status = Some(event.get_status_channel_combo());
+                event.write_midi(writer, options)?;
+            }
+            event => {
+                *running_status = None;
+                event.write_midi(writer, options)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses an MTrk event using the state left behind by the previous event in the track, see
+    /// [`Event::try_from_state`]
+    pub fn try_from_state<ITER: Iterator<Item = u8>>(
+        value: &mut ITER,
+        state: &mut TrackParseState,
+    ) -> Result<Self, TrackError> {
         if let Some(dt) = MTrkEvent::try_get_delta_time(value) {
             Ok(MTrkEvent {
                 delta_time: dt,
-                event: Event::try_from(IteratorWrapper(value))?,
+                event: Event::try_from_state(value, state)?,
             })
         } else {
             Err(TrackError::EOF)
         }
     }
-}
 
-impl MTrkEvent {
     /// Gets the delta time as a variable length
     pub fn try_get_delta_time<ITER: Iterator<Item = u8>>(iter: &mut ITER) -> Option<u32> {
-        let mut time_bytes = vec![];
+        const MASK: u8 = 0x7F;
 
-        // Collect from iterator until delta time bytes are done
-        for byte in iter.by_ref() {
-            // Check if msb is 1, if not then this is the last delta time
-            let msb_one = MTrkEvent::msb_is_one(byte);
+        let mut result: Option<u32> = None;
 
-            time_bytes.push(byte);
+        // Concat bytes from the iterator until delta time bytes are done
+        for byte in iter.by_ref() {
+            let value = result.unwrap_or(0);
+            result = Some((value << 7) | (byte & MASK) as u32);
 
-            if !msb_one {
+            // Check if msb is 1, if not then this is the last delta time
+            if !MTrkEvent::msb_is_one(byte) {
                 break;
             }
         }
 
-        const MASK: u8 = 0x7F;
-
-        if time_bytes.is_empty() {
-            return None;
-        }
-
-        // Concat all bytes together
-        let mut result: u32 = 0;
-        for byte in time_bytes {
-            result <<= 7;
-            result |= (byte & MASK) as u32;
-        }
-
-        Some(result)
+        result
     }
 
     /// Goes backwards from length to variable length vector of bytes
@@ -210,12 +600,40 @@ pub enum Event {
     MetaEvent(MetaEvent),
 }
 
+impl From<MidiEvent> for Event {
+    fn from(value: MidiEvent) -> Self {
+        Self::MidiEvent(value)
+    }
+}
+
+impl From<SysexEvent> for Event {
+    fn from(value: SysexEvent) -> Self {
+        Self::SysexEvent(value)
+    }
+}
+
+impl From<MetaEvent> for Event {
+    fn from(value: MetaEvent) -> Self {
+        Self::MetaEvent(value)
+    }
+}
+
 impl MidiWriteable for Event {
     fn to_midi_bytes(self) -> Vec<u8> {
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
+
+impl WriteMidi for Event {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
         match self {
-            Self::MidiEvent(event) => event.to_midi_bytes(),
-            Self::SysexEvent(event) => event.to_midi_bytes(),
-            Self::MetaEvent(event) => event.to_midi_bytes(),
+            Self::MidiEvent(event) => event.write_midi(writer, options),
+            Self::SysexEvent(event) => event.write_midi(writer, options),
+            Self::MetaEvent(event) => event.write_midi(writer, options),
         }
     }
 }
@@ -226,22 +644,55 @@ where
 {
     type Error = TrackError;
     fn try_from(value: IteratorWrapper<&mut ITER>) -> Result<Self, Self::Error> {
-        let mut peek = value.0.peekable();
+        Event::try_from_state(value.0, &mut TrackParseState::default())
+    }
+}
 
-        let prefix = peek.peek().ok_or(TrackError::OutOfSpace)?;
+impl Event {
+    /// Parses an event, falling back to the running status when the next byte is a data byte
+    /// rather than a status byte. MIDI channel events set the running status, while sysex and
+    /// meta events cancel it. `F7` sysex packets are continuations while a divided sysex message
+    /// is open and escapes otherwise
+    pub fn try_from_state<ITER: Iterator<Item = u8>>(
+        value: &mut ITER,
+        state: &mut TrackParseState,
+    ) -> Result<Self, TrackError> {
+        let mut peek = value.peekable();
+
+        let prefix = *peek.peek().ok_or(TrackError::OutOfSpace)?;
 
         match prefix {
-            status if (0x80..=0xEF).contains(status) => Ok(Event::MidiEvent(MidiEvent::try_from(
-                IteratorWrapper(&mut peek),
-            )?)),
+            status if (0x80..=0xEF).contains(&status) => {
+                peek.next();
+                state.running_status = Some(status);
+                Ok(Event::MidiEvent(MidiEvent::try_read_data(
+                    status, &mut peek,
+                )?))
+            }
+
+            data if data < 0x80 => {
+                let status = state
+                    .running_status
+                    .ok_or(TrackError::MissingRunningStatus)?;
+                Ok(Event::MidiEvent(MidiEvent::try_read_data(
+                    status, &mut peek,
+                )?))
+            }
 
-            system if (0xF0..0xFF).contains(system) => Ok(Event::SysexEvent(SysexEvent::try_from(
-                IteratorWrapper(&mut peek),
-            )?)),
+            0xF0 | 0xF7 => {
+                state.running_status = None;
+                Ok(Event::SysexEvent(SysexEvent::try_from_divided(
+                    &mut peek,
+                    &mut state.divided_sysex,
+                )?))
+            }
 
-            0xFF => Ok(Event::MetaEvent(MetaEvent::try_from(IteratorWrapper(
-                &mut peek,
-            ))?)),
+            0xFF => {
+                state.running_status = None;
+                Ok(Event::MetaEvent(MetaEvent::try_from(IteratorWrapper(
+                    &mut peek,
+                ))?))
+            }
 
             _ => Err(TrackError::InvalidFormat),
         }
@@ -250,7 +701,14 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::MTrkEvent;
+    use crate::{
+        chunk::{ChunkParseError, ErrorLocation, OutOfRange},
+        writer::MidiWriteable,
+    };
+
+    use super::{
+        event::MidiEvent, meta::MetaEvent, Event, MTrkEvent, TrackChunk, TrackError, TrackEvents,
+    };
 
     #[test]
     fn delta_time_parsed() {
@@ -269,4 +727,161 @@ mod tests {
 
         assert_eq!(bytes, expected)
     }
+
+    #[test]
+    fn running_status_reuses_previous_status() {
+        let bytes = vec![
+            0x00, 0x90, 0x3C, 0x40, // Note on, explicit status
+            0x10, 0x3E, 0x40, // Note on, running status
+            0x10, 0x3C, 0x00, // Note on (velocity 0), running status
+            0x00, 0xFF, 0x2F, 0x00, // End of track
+        ];
+
+        let track = TrackChunk::try_from(bytes).expect("Parse track with running status");
+        let events: Vec<_> = track
+            .mtrk_events
+            .into_iter()
+            .map(|e| e.event.to_midi_bytes())
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                vec![0x90, 0x3C, 0x40],
+                vec![0x90, 0x3E, 0x40],
+                vec![0x90, 0x3C, 0x00],
+                vec![0xFF, 0x2F, 0x00],
+            ]
+        )
+    }
+
+    #[test]
+    fn meta_event_cancels_running_status() {
+        let bytes = vec![
+            0x00, 0xC0, 0x05, // Program change
+            0x00, 0xFF, 0x01, 0x00, // Empty text meta event
+            0x00, 0x06, // Data byte with no running status
+        ];
+
+        let error = TrackChunk::try_from(bytes).expect_err("Missing running status");
+        assert_eq!(
+            error.location(),
+            Some(&ErrorLocation {
+                offset: 8,
+                chunk: 0,
+                event: Some(2),
+                byte: Some(0x06),
+            })
+        );
+        assert!(matches!(
+            error,
+            ChunkParseError::TrackParseError {
+                source: TrackError::MissingRunningStatus,
+                ..
+            }
+        ))
+    }
+
+    #[test]
+    fn absolute_events_accumulate_delta_times() {
+        let bytes = vec![
+            0x00, 0x90, 0x3C, 0x40, // Note on
+            0x81, 0x40, 0x80, 0x3C, 0x40, // Note off after 192 ticks
+            0x10, 0xFF, 0x2F, 0x00, // End of track after 16 ticks
+        ];
+
+        let track = TrackChunk::try_from(bytes).expect("Parse track");
+        let ticks: Vec<_> = track.absolute_events().map(|(tick, _)| tick).collect();
+
+        assert_eq!(ticks, vec![0, 192, 208])
+    }
+
+    #[test]
+    fn absolute_events_round_trip_to_delta_times() {
+        let bytes = vec![
+            0x00, 0x90, 0x3C, 0x40, // Note on
+            0x81, 0x40, 0x80, 0x3C, 0x40, // Note off after 192 ticks
+            0x10, 0xFF, 0x2F, 0x00, // End of track after 16 ticks
+        ];
+
+        let track = TrackChunk::try_from(bytes).expect("Parse track");
+        let mut events: Vec<_> = track
+            .absolute_events()
+            .map(|(tick, event)| (tick, event.clone()))
+            .collect();
+        events.reverse();
+
+        assert_eq!(TrackChunk::from_absolute_events(events), Ok(track));
+
+        let end = MTrkEvent::MAX_DELTA_TIME as u64 + 1;
+        assert_eq!(
+            TrackChunk::from_absolute_events([(end, Event::MetaEvent(MetaEvent::EndOfTrack))]),
+            Err(OutOfRange {
+                field: "delta time",
+                value: end as i64
+            })
+        )
+    }
+
+    #[test]
+    fn track_builder_appends_end_of_track() {
+        let track = TrackChunk::builder()
+            .event(0, MidiEvent::note_on(0, 60, 100).unwrap())
+            .event_at(96, MidiEvent::note_off(0, 60, 0).unwrap())
+            .build()
+            .expect("Build track");
+
+        assert_eq!(
+            track.clone().to_midi_bytes(),
+            vec![
+                0x00, 0x90, 0x3C, 0x64, //
+                0x60, 0x80, 0x3C, 0x00, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(
+            track.events().last().map(MTrkEvent::event),
+            Some(&MetaEvent::EndOfTrack.into())
+        )
+    }
+
+    #[test]
+    fn track_builder_rejects_invalid_timing() {
+        assert!(TrackChunk::builder()
+            .event(MTrkEvent::MAX_DELTA_TIME + 1, MetaEvent::EndOfTrack)
+            .build()
+            .is_err());
+        assert!(TrackChunk::builder()
+            .event_at(10, MetaEvent::Text("A".to_string()))
+            .event_at(5, MetaEvent::EndOfTrack)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn track_events_decode_lazily_and_stop_at_errors() {
+        let bytes = [
+            0x00, 0x90, 0x3C, 0x40, // Note on
+            0x10, 0x80, 0x3C, 0x40, // Note off
+            0x00, 0xF4, // Undefined status
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+
+        let mut events = TrackEvents::new(&bytes);
+        let first = events.next().expect("First event").expect("Parse note on");
+        assert_eq!(
+            first.event,
+            Event::MidiEvent(MidiEvent::note_on(0, 0x3C, 0x40).unwrap())
+        );
+        assert_eq!(events.position(), 4);
+
+        assert!(events.next().expect("Second event").is_ok());
+
+        let error = events
+            .next()
+            .expect("Error item")
+            .expect_err("Invalid status");
+        assert_eq!(error.location().and_then(|l| l.byte), Some(0xF4));
+        assert!(events.next().is_none());
+    }
 }
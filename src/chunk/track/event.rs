@@ -1,6 +1,22 @@
 //! Status parsing trait and implementation
 
-use crate::{reader::Yieldable, writer::MidiWriteable};
+use alloc::{vec, vec::Vec};
+
+use crate::io::{self, Write};
+
+use crate::{
+    chunk::{
+        track::{
+            value::{Channel, U14, U7},
+            TrackError,
+        },
+        OutOfRange,
+    },
+    controller::Controller,
+    pitch::Pitch,
+    reader::Yieldable,
+    writer::{MidiWriteable, WriteMidi, WriteOptions},
+};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -11,72 +27,172 @@ use serde::{Deserialize, Serialize};
 pub enum MidiEvent {
     /// Turn Off event
     /// This message is sent whena  note is released
-    NoteOff(u8, NoteMeta),
+    NoteOff(Channel, NoteMeta),
     /// Turn On event
     /// This message is sent when a note is depressed
-    NoteOn(u8, NoteMeta),
+    NoteOn(Channel, NoteMeta),
     /// Polyphonic Key Pressure
     /// This message is most often sent by pressing down a key after it "bottoms out"
-    PolyphonicKeyPressure(u8, NoteMeta),
+    PolyphonicKeyPressure(Channel, NoteMeta),
     /// Control change
     /// This message is sent when a controller value changes. Controllers include devices such as
     /// pedals and levers. Certain controller numbers are reserved.
-    ControlChange(u8, ControlChange),
+    ControlChange(Channel, ControlChange),
     /// Program change.
     /// This message is sent when the patch number changes
-    ProgramChange(u8, u8),
+    ProgramChange(Channel, U7),
     /// Channel Pressure
     /// This message is most often sent by pressing down on a key after it "bottoms out"
-    ChannelPressure(u8, u8),
+    ChannelPressure(Channel, U7),
     /// Pitch Wheel Change
     /// This message is sent to indicate a change in the pitch wheel as measured by a fourteen bit
     /// value.
-    PitchWheelChange(u8, u16),
+    PitchWheelChange(Channel, U14),
 }
 
 impl MidiWriteable for MidiEvent {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let status_byte = self.get_status_channel_combo();
-        let mut bytes = vec![status_byte];
-
-        let extra = match self {
-            Self::NoteOff(_, notemeta)
-            | Self::NoteOn(_, notemeta)
-            | Self::PolyphonicKeyPressure(_, notemeta) => notemeta.to_midi_bytes(),
-            Self::ControlChange(_, control_change) => control_change.to_midi_bytes(),
-            Self::ProgramChange(_, val) | Self::ChannelPressure(_, val) => val.to_midi_bytes(),
-            Self::PitchWheelChange(_, val) => val.to_midi_bytes(),
-        };
-
-        bytes.extend(extra.iter());
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
 
-        bytes
+impl WriteMidi for MidiEvent {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        _options: WriteOptions,
+    ) -> io::Result<()> {
+        let (data, len) = self.data_bytes();
+        writer.write_all(&[self.get_status_channel_combo()])?;
+        writer.write_all(&data[..len])
     }
 }
 
 impl MidiEvent {
-    /// Combines the channel and current type's status identifier into a single byte
-    pub fn get_status_channel_combo(&self) -> u8 {
+    /// Creates a note off event, failing if the channel is above 15 or the key or velocity are
+    /// above 127
+    pub fn note_off(channel: u8, key: u8, velocity: u8) -> Result<Self, OutOfRange> {
+        Ok(Self::NoteOff(
+            Channel::new(channel)?,
+            NoteMeta::new(key, velocity)?,
+        ))
+    }
+
+    /// Creates a note on event, failing if the channel is above 15 or the key or velocity are
+    /// above 127
+    pub fn note_on(channel: u8, key: u8, velocity: u8) -> Result<Self, OutOfRange> {
+        Ok(Self::NoteOn(
+            Channel::new(channel)?,
+            NoteMeta::new(key, velocity)?,
+        ))
+    }
+
+    /// Creates a polyphonic key pressure event, failing if the channel is above 15 or the key or
+    /// pressure are above 127
+    pub fn polyphonic_key_pressure(channel: u8, key: u8, pressure: u8) -> Result<Self, OutOfRange> {
+        Ok(Self::PolyphonicKeyPressure(
+            Channel::new(channel)?,
+            NoteMeta::new(key, pressure)?,
+        ))
+    }
+
+    /// Creates a control change event, failing if the channel is above 15 or the controller
+    /// number or value are above 127
+    pub fn control_change(
+        channel: u8,
+        controller_number: u8,
+        new_value: u8,
+    ) -> Result<Self, OutOfRange> {
+        Ok(Self::ControlChange(
+            Channel::new(channel)?,
+            ControlChange::new(controller_number, new_value)?,
+        ))
+    }
+
+    /// Creates a program change event, failing if the channel is above 15 or the program is
+    /// above 127
+    pub fn program_change(channel: u8, program: u8) -> Result<Self, OutOfRange> {
+        Ok(Self::ProgramChange(
+            Channel::new(channel)?,
+            U7::checked("program", program)?,
+        ))
+    }
+
+    /// Creates a channel pressure event, failing if the channel is above 15 or the pressure is
+    /// above 127
+    pub fn channel_pressure(channel: u8, pressure: u8) -> Result<Self, OutOfRange> {
+        Ok(Self::ChannelPressure(
+            Channel::new(channel)?,
+            U7::checked("pressure", pressure)?,
+        ))
+    }
+
+    /// Creates a pitch wheel change event, failing if the channel is above 15 or the value
+    /// doesn't fit in 14 bits
+    pub fn pitch_wheel_change(channel: u8, value: u16) -> Result<Self, OutOfRange> {
+        Ok(Self::PitchWheelChange(
+            Channel::new(channel)?,
+            U14::checked("pitch wheel", value)?,
+        ))
+    }
+
+    /// Gets the channel the event is sent on
+    pub fn channel(&self) -> Channel {
         match self {
-            Self::NoteOff(channel, _) => 0b10000000 | channel,
-            Self::NoteOn(channel, _) => 0b10010000 | channel,
-            Self::PolyphonicKeyPressure(channel, _) => 0b10100000 | channel,
-            Self::ControlChange(channel, _) => 0b10110000 | channel,
-            Self::ProgramChange(channel, _) => 0b11000000 | channel,
-            Self::ChannelPressure(channel, _) => 0b11010000 | channel,
-            Self::PitchWheelChange(channel, _) => 0b11100000 | channel,
+            Self::NoteOff(channel, _)
+            | Self::NoteOn(channel, _)
+            | Self::PolyphonicKeyPressure(channel, _)
+            | Self::ControlChange(channel, _)
+            | Self::ProgramChange(channel, _)
+            | Self::ChannelPressure(channel, _)
+            | Self::PitchWheelChange(channel, _) => *channel,
+        }
+    }
+
+    /// Gets the data bytes that follow the status byte, and how many of them are used
+    pub(crate) fn data_bytes(&self) -> ([u8; 2], usize) {
+        match *self {
+            Self::NoteOff(_, note)
+            | Self::NoteOn(_, note)
+            | Self::PolyphonicKeyPressure(_, note) => ([note.key.get(), note.velocity.get()], 2),
+            Self::ControlChange(_, change) => {
+                ([change.controller_number.get(), change.new_value.get()], 2)
+            }
+            Self::ProgramChange(_, val) | Self::ChannelPressure(_, val) => ([val.get(), 0], 1),
+            Self::PitchWheelChange(_, val) => ([val.lsb().get(), val.msb().get()], 2),
         }
     }
+
+    /// Combines the channel and current type's status identifier into a single byte
+    pub fn get_status_channel_combo(&self) -> u8 {
+        let status = match self {
+            Self::NoteOff(..) => 0b10000000,
+            Self::NoteOn(..) => 0b10010000,
+            Self::PolyphonicKeyPressure(..) => 0b10100000,
+            Self::ControlChange(..) => 0b10110000,
+            Self::ProgramChange(..) => 0b11000000,
+            Self::ChannelPressure(..) => 0b11010000,
+            Self::PitchWheelChange(..) => 0b11100000,
+        };
+        status | self.channel().get()
+    }
 }
 
 /// Error type for an unsupported error type
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UnsupportedStatusCode(u8);
 
+impl UnsupportedStatusCode {
+    /// Gets the full status byte that was rejected, including its channel nibble
+    pub fn status(&self) -> u8 {
+        self.0
+    }
+}
+
 impl core::error::Error for UnsupportedStatusCode {}
 impl core::fmt::Display for UnsupportedStatusCode {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write![f, "Unsupported Status Code {}", self.0]
+        write![f, "Unsupported Status Code {:#04X}", self.0]
     }
 }
 /// Wrapper around iterator to prevent trait implementation sillyness
@@ -85,72 +201,110 @@ impl<ITER> TryFrom<IteratorWrapper<&mut ITER>> for MidiEvent
 where
     ITER: Iterator<Item = u8>,
 {
-    type Error = UnsupportedStatusCode;
+    type Error = TrackError;
     fn try_from(value: IteratorWrapper<&mut ITER>) -> Result<Self, Self::Error> {
         let value = value.0;
         let status = value.get(1)[0];
-        let channel = status & 0x0F;
-        let status = status >> 4;
-
-        match status {
-            0b1000 => {
-                let reads = value.get(2);
-                Ok(Self::NoteOff(
-                    channel,
-                    NoteMeta {
-                        key: reads[0],
-                        velocity: reads[1],
-                    },
-                ))
-            }
 
-            0b1001 => {
-                let reads = value.get(2);
-                Ok(Self::NoteOn(
-                    channel,
-                    NoteMeta {
-                        key: reads[0],
-                        velocity: reads[1],
-                    },
-                ))
-            }
-
-            0b1011 => {
-                let reads = value.get(2);
-                Ok(Self::ControlChange(
-                    channel,
-                    ControlChange {
-                        controller_number: reads[0],
-                        new_value: reads[1],
-                    },
-                ))
-            }
+        MidiEvent::try_from_status(status, value)
+    }
+}
 
-            0b1100 => {
-                let reads = value.get(1);
-                Ok(Self::ProgramChange(channel, reads[0]))
-            }
+impl MidiEvent {
+    /// Gets the number of data bytes that follow a channel event's status byte
+    pub fn data_len(status: u8) -> Option<usize> {
+        match status >> 4 {
+            0b1000 | 0b1001 | 0b1010 | 0b1011 | 0b1110 => Some(2),
+            0b1100 | 0b1101 => Some(1),
+            _ => None,
+        }
+    }
 
-            0b1101 => {
-                let reads = value.get(1);
-                Ok(Self::ChannelPressure(channel, reads[0]))
-            }
+    /// Reads exactly as many data bytes as the status byte requires before parsing them, so a
+    /// truncated event is reported instead of read past
+    pub(crate) fn try_read_data<ITER: Iterator<Item = u8>>(
+        status: u8,
+        value: &mut ITER,
+    ) -> Result<Self, TrackError> {
+        let len = MidiEvent::data_len(status).ok_or(UnsupportedStatusCode(status))?;
+        let mut data = [0; 2];
+        for byte in data.iter_mut().take(len) {
+            *byte = value.next().ok_or(TrackError::OutOfSpace)?;
+        }
 
-            0b1110 => {
-                let reads = value.get(2);
+        MidiEvent::from_data(status, &data[..len])
+    }
 
-                const MASK: u8 = 0x7;
+    /// Parses a MIDI event's data bytes from an iterator given an already known status byte. This
+    /// is used both for freshly read status bytes and for running status, where the status byte
+    /// is omitted from the stream and the previous one is reused
+    pub fn try_from_status<ITER: Iterator<Item = u8>>(
+        status: u8,
+        value: &mut ITER,
+    ) -> Result<Self, TrackError> {
+        let len = MidiEvent::data_len(status).ok_or(UnsupportedStatusCode(status))?;
+        let reads = value.get(len);
+        if reads.len() != len {
+            return Err(TrackError::OutOfSpace);
+        }
 
-                let mut result: u16 = 0;
-                for byte in reads.iter().rev() {
-                    result <<= 7;
-                    result |= (byte & MASK) as u16;
-                }
+        MidiEvent::from_data(status, &reads)
+    }
 
-                Ok(Self::PitchWheelChange(channel, result))
-            }
+    /// Builds a MIDI event from its status byte and a slice holding exactly
+    /// [`MidiEvent::data_len`] data bytes, failing if a data byte has its high bit set
+    pub(crate) fn from_data(status: u8, reads: &[u8]) -> Result<Self, TrackError> {
+        let mut values = [U7::MIN; 2];
+        for (value, byte) in values.iter_mut().zip(reads) {
+            *value = U7::new(*byte).map_err(|_| TrackError::InvalidDataByte(*byte))?;
+        }
 
-            code => Err(UnsupportedStatusCode(code)),
+        let channel = Channel::from_low_bits(status);
+        let data = |index: usize| values[index];
+
+        match status >> 4 {
+            0b1000 => Ok(Self::NoteOff(
+                channel,
+                NoteMeta {
+                    key: data(0),
+                    velocity: data(1),
+                },
+            )),
+
+            0b1001 => Ok(Self::NoteOn(
+                channel,
+                NoteMeta {
+                    key: data(0),
+                    velocity: data(1),
+                },
+            )),
+
+            0b1010 => Ok(Self::PolyphonicKeyPressure(
+                channel,
+                NoteMeta {
+                    key: data(0),
+                    velocity: data(1),
+                },
+            )),
+
+            0b1011 => Ok(Self::ControlChange(
+                channel,
+                ControlChange {
+                    controller_number: data(0),
+                    new_value: data(1),
+                },
+            )),
+
+            0b1100 => Ok(Self::ProgramChange(channel, data(0))),
+
+            0b1101 => Ok(Self::ChannelPressure(channel, data(0))),
+
+            0b1110 => Ok(Self::PitchWheelChange(
+                channel,
+                U14::from_lsb_msb(data(0), data(1)),
+            )),
+
+            _ => Err(UnsupportedStatusCode(status).into()),
         }
     }
 }
@@ -160,14 +314,49 @@ where
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NoteMeta {
     /// Note key
-    key: u8,
+    key: U7,
     /// Note velocity
-    velocity: u8,
+    velocity: U7,
+}
+
+impl NoteMeta {
+    /// Creates new note metadata, failing if the key or velocity are above 127
+    pub fn new(key: u8, velocity: u8) -> Result<Self, OutOfRange> {
+        Ok(Self {
+            key: U7::checked("key", key)?,
+            velocity: U7::checked("velocity", velocity)?,
+        })
+    }
+
+    /// Creates new note metadata from values that are already known to be in range
+    pub const fn from_values(key: U7, velocity: U7) -> Self {
+        Self { key, velocity }
+    }
+
+    /// Gets the note's key
+    pub fn key(&self) -> U7 {
+        self.key
+    }
+
+    /// Gets the note's velocity
+    pub fn velocity(&self) -> U7 {
+        self.velocity
+    }
+
+    /// Gets the pitch of the note's key
+    pub fn pitch(&self) -> Pitch {
+        Pitch::new(self.key)
+    }
+
+    /// Returns a copy of the note metadata with a different velocity
+    pub(crate) fn with_velocity(self, velocity: U7) -> Self {
+        Self { velocity, ..self }
+    }
 }
 
 impl MidiWriteable for NoteMeta {
     fn to_midi_bytes(self) -> Vec<u8> {
-        vec![self.key, self.velocity]
+        vec![self.key.get(), self.velocity.get()]
     }
 }
 
@@ -176,38 +365,85 @@ impl MidiWriteable for NoteMeta {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ControlChange {
     /// Controller number
-    controller_number: u8,
+    controller_number: U7,
     /// New value
-    new_value: u8,
+    new_value: U7,
+}
+
+impl ControlChange {
+    /// Creates a new control change, failing if the controller number or value are above 127
+    pub fn new(controller_number: u8, new_value: u8) -> Result<Self, OutOfRange> {
+        Ok(Self {
+            controller_number: U7::checked("controller number", controller_number)?,
+            new_value: U7::checked("controller value", new_value)?,
+        })
+    }
+
+    /// Creates a new control change from values that are already known to be in range
+    pub const fn from_values(controller_number: U7, new_value: U7) -> Self {
+        Self {
+            controller_number,
+            new_value,
+        }
+    }
+
+    /// Gets the number of the controller being changed
+    pub fn controller_number(&self) -> U7 {
+        self.controller_number
+    }
+
+    /// Gets the controller being changed
+    pub fn controller(&self) -> Controller {
+        Controller::from_number(self.controller_number)
+    }
+
+    /// Gets the controller's new value
+    pub fn new_value(&self) -> U7 {
+        self.new_value
+    }
 }
 
 impl MidiWriteable for ControlChange {
     fn to_midi_bytes(self) -> Vec<u8> {
-        vec![self.controller_number, self.new_value]
+        vec![self.controller_number.get(), self.new_value.get()]
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{chunk::track::event::UnsupportedStatusCode, writer::MidiWriteable};
+    use crate::{
+        chunk::track::{event::UnsupportedStatusCode, TrackError},
+        writer::MidiWriteable,
+    };
 
-    use super::{IteratorWrapper, MidiEvent, NoteMeta};
+    use super::{Channel, ControlChange, IteratorWrapper, MidiEvent, NoteMeta, U14, U7};
 
     #[test]
     fn midi_event_status_parsing() {
         let status_channel = 0b10001111;
         let key = 0b01010101;
-        let velocity = 0b11111111;
+        let velocity = 0b01111111;
 
         let mut stream = [status_channel, key, velocity].into_iter();
         let status =
             MidiEvent::try_from(IteratorWrapper(&mut stream)).expect("Parse off note signal");
 
-        let expected = MidiEvent::NoteOff(0x0F, NoteMeta { key, velocity });
+        let expected = MidiEvent::NoteOff(Channel::MAX, NoteMeta::new(key, velocity).unwrap());
 
         assert_eq!(status, expected)
     }
 
+    #[test]
+    fn midi_event_status_parsing_fails_on_invalid_data_byte() {
+        let status_channel = 0b10001111;
+        let key = 0b01010101;
+        let velocity = 0b11111111;
+
+        let mut stream = [status_channel, key, velocity].into_iter();
+        let status = MidiEvent::try_from(IteratorWrapper(&mut stream));
+        assert_eq!(status, Err(TrackError::InvalidDataByte(0b11111111)));
+    }
+
     #[test]
     fn midi_event_status_parsing_fails_on_invalid_status() {
         let status_channel = 0b00101111;
@@ -216,20 +452,63 @@ mod tests {
 
         let mut stream = [status_channel, key, velocity].into_iter();
         let status = MidiEvent::try_from(IteratorWrapper(&mut stream));
-        assert_eq!(status, Err(UnsupportedStatusCode(0b0010)));
+        assert_eq!(
+            status,
+            Err(TrackError::UnsupportedStatusCode(UnsupportedStatusCode(
+                0b00101111
+            )))
+        );
     }
 
     #[test]
     fn midi_event_backwards_parses_to_bytes() {
         let key = 0b01010101;
-        let velocity = 0b11111111;
+        let velocity = 0b01111111;
 
-        let expected = MidiEvent::NoteOff(0x0F, NoteMeta { key, velocity });
+        let expected = MidiEvent::NoteOff(Channel::MAX, NoteMeta::new(key, velocity).unwrap());
 
-        let mut stream = expected.clone().to_midi_bytes().into_iter();
+        let mut stream = expected.to_midi_bytes().into_iter();
         let bytes =
             MidiEvent::try_from(IteratorWrapper(&mut stream)).expect("Parse from serialized bytes");
 
         assert_eq!(bytes, expected)
     }
+
+    #[test]
+    fn midi_event_constructors_validate_ranges() {
+        assert_eq!(
+            MidiEvent::note_on(3, 60, 100),
+            Ok(MidiEvent::NoteOn(
+                Channel::new(3).unwrap(),
+                NoteMeta::new(60, 100).unwrap()
+            ))
+        );
+        assert!(MidiEvent::note_on(16, 60, 100).is_err());
+        assert!(MidiEvent::note_off(0, 128, 0).is_err());
+        assert!(MidiEvent::program_change(0, 0x80).is_err());
+        assert!(MidiEvent::pitch_wheel_change(0, 0x4000).is_err());
+        assert!(ControlChange::new(7, 200).is_err());
+    }
+
+    #[test]
+    fn pitch_wheel_is_sent_lsb_first() {
+        let event = MidiEvent::pitch_wheel_change(2, 0x1F85).unwrap();
+        assert_eq!(event.to_midi_bytes(), vec![0xE2, 0x05, 0x3F]);
+
+        let mut stream = [0xE2, 0x00, 0x40].into_iter();
+        let parsed = MidiEvent::try_from(IteratorWrapper(&mut stream)).expect("Parse pitch wheel");
+        assert_eq!(
+            parsed,
+            MidiEvent::PitchWheelChange(Channel::new(2).unwrap(), U14::CENTER)
+        );
+    }
+
+    #[test]
+    fn typed_values_keep_status_and_data_bytes_valid() {
+        let event = MidiEvent::ControlChange(
+            Channel::saturating(200),
+            ControlChange::from_values(U7::new(7).unwrap(), U7::saturating(0xFF)),
+        );
+        assert_eq!(event.to_midi_bytes(), vec![0xBF, 0x07, 0x7F]);
+    }
 }
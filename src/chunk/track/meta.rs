@@ -1,7 +1,14 @@
 //! Meta Event Structs and Parsing
 
+use alloc::{borrow::Cow, string::String, vec, vec::Vec};
+
 use super::{event::IteratorWrapper, TrackError};
-use crate::{chunk::track::MTrkEvent, reader::Yieldable, writer::MidiWriteable};
+use crate::{
+    chunk::{header::Division, track::MTrkEvent, OutOfRange},
+    io::{self, Write},
+    reader::Yieldable,
+    writer::{write_vlq, MidiWriteable, WriteMidi, WriteOptions},
+};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -40,7 +47,7 @@ pub enum MetaEvent {
     KeySignature(KeySignature),
     /// Sequencer Specific, tag 0x7f
     SequencerSpecific(Vec<u8>),
-    /// An unknown meta event
+    /// An unknown meta event, or a time or key signature whose values are out of range
     UnknownRaw(u8, Vec<u8>),
 }
 
@@ -70,70 +77,248 @@ impl MetaEvent {
 
 impl MidiWriteable for MetaEvent {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let tag_byte = self.get_tag();
-        let mut bytes = vec![0xFF, tag_byte];
-
-        let payload_bytes = match self {
-            Self::SequenceNumber(val) => val.to_midi_bytes(),
-            Self::Text(val) => val.to_midi_bytes(),
-            Self::Copyright(val) => val.to_midi_bytes(),
-            Self::TrackName(val) => val.to_midi_bytes(),
-            Self::InstrumentName(val) => val.to_midi_bytes(),
-            Self::Lyric(val) => val.to_midi_bytes(),
-            Self::Marker(val) => val.to_midi_bytes(),
-            Self::CuePoint(val) => val,
-            Self::MidiChannelPrefix(val) => val.to_midi_bytes(),
-            Self::EndOfTrack => vec![],
-            Self::Tempo(val) => {
-                let res = val.to_midi_bytes();
-                res[1..].to_vec()
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
+
+impl WriteMidi for MetaEvent {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        _options: WriteOptions,
+    ) -> io::Result<()> {
+        let payload = self.payload();
+
+        writer.write_all(&[0xFF, self.get_tag()])?;
+        write_vlq(writer, payload.len() as u32)?;
+        writer.write_all(&payload)
+    }
+}
+
+impl MetaEvent {
+    /// Gets the event's payload, borrowing it where the event already holds the bytes
+    fn payload(&self) -> Cow<'_, [u8]> {
+        match self {
+            Self::SequenceNumber(val) => Cow::Owned(val.to_midi_bytes()),
+            Self::Text(val)
+            | Self::Copyright(val)
+            | Self::TrackName(val)
+            | Self::InstrumentName(val)
+            | Self::Lyric(val)
+            | Self::Marker(val) => Cow::Borrowed(val.as_bytes()),
+            Self::CuePoint(val) | Self::SequencerSpecific(val) | Self::UnknownRaw(_, val) => {
+                Cow::Borrowed(val)
             }
-            Self::SmpteOffset(val) => val.to_midi_bytes(),
-            Self::TimeSignature(val) => val.to_midi_bytes(),
-            Self::KeySignature(val) => val.to_midi_bytes(),
-            Self::SequencerSpecific(val) => val,
-            Self::UnknownRaw(_, val) => val,
-        };
+            Self::MidiChannelPrefix(val) => Cow::Owned(val.to_midi_bytes()),
+            Self::EndOfTrack => Cow::Borrowed(&[]),
+            Self::Tempo(val) => Cow::Owned(val.to_be_bytes()[1..].to_vec()),
+            Self::SmpteOffset(val) => Cow::Owned(val.to_midi_bytes()),
+            Self::TimeSignature(val) => Cow::Owned(val.to_midi_bytes()),
+            Self::KeySignature(val) => Cow::Owned(val.to_midi_bytes()),
+        }
+    }
+}
+
+/// Tonic names of major keys, indexed by the number of sharps (positive) or flats (negative)
+/// plus 7
+const MAJOR_TONICS: [&str; 15] = [
+    "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+];
 
-        let length = payload_bytes.len() as u32;
-        let len_vlq = MTrkEvent::to_midi_vlq(length);
+/// Tonic names of minor keys, indexed by the number of sharps (positive) or flats (negative)
+/// plus 7
+const MINOR_TONICS: [&str; 15] = [
+    "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#", "G#", "D#", "A#",
+];
+
+/// Whether a key is major or minor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Mode {
+    /// A major key
+    #[default]
+    Major,
+    /// A minor key, using the natural minor scale
+    Minor,
+}
 
-        bytes.extend(len_vlq.iter());
-        bytes.extend(payload_bytes.iter());
+impl Mode {
+    /// Semitones above the tonic of each degree of the mode's scale
+    fn intervals(self) -> [u8; 7] {
+        match self {
+            Self::Major => [0, 2, 4, 5, 7, 9, 11],
+            Self::Minor => [0, 2, 3, 5, 7, 8, 10],
+        }
+    }
+
+    /// Gets the other mode
+    fn other(self) -> Self {
+        match self {
+            Self::Major => Self::Minor,
+            Self::Minor => Self::Major,
+        }
+    }
+}
 
-        bytes
+impl core::fmt::Display for Mode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Major => write![f, "major"],
+            Self::Minor => write![f, "minor"],
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-/// A key signature
+/// A key signature. Displays and parses as key names such as `F# minor` or `Bb major`
 pub struct KeySignature {
     /// Sharps and flats
     sharps_flats: i8,
-    /// True if in major false if in minor
-    major_minor: bool,
+    /// Whether the key is major or minor
+    mode: Mode,
 }
 
-impl MidiWriteable for KeySignature {
-    fn to_midi_bytes(self) -> Vec<u8> {
-        let KeySignature {
-            sharps_flats,
-            major_minor,
-        } = self;
+impl KeySignature {
+    /// Creates a new key signature from a number of sharps (positive) or flats (negative),
+    /// failing if there are more than 7 of either
+    pub fn new(sharps_flats: i8, mode: Mode) -> Result<Self, OutOfRange> {
+        Ok(Self {
+            sharps_flats: OutOfRange::check("sharps and flats", sharps_flats, -7, 7)?,
+            mode,
+        })
+    }
+
+    /// Gets the number of sharps (positive) or flats (negative)
+    pub fn sharps_flats(&self) -> i8 {
+        self.sharps_flats
+    }
 
-        let mut bytes = sharps_flats.to_midi_bytes();
-        let major_minor_bit = if major_minor {
-            // Some data may be lost here as we only know *if* major was not 0 it's true. But it's
-            // only ever used for this so it's not too much of an issue
-            1
-        } else {
-            0
+    /// Gets whether the key is major or minor
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns true if the key is minor
+    pub fn is_minor(&self) -> bool {
+        self.mode == Mode::Minor
+    }
+
+    /// Gets the pitch class of the key's tonic, the number of semitones above C from 0 to 11
+    pub fn tonic(&self) -> u8 {
+        let major = (self.sharps_flats as i16 * 7).rem_euclid(12) as u8;
+        match self.mode {
+            Mode::Major => major,
+            Mode::Minor => (major + 9) % 12,
+        }
+    }
+
+    /// Gets the name of the key's tonic, such as `F#` or `Bb`
+    pub fn tonic_name(&self) -> &'static str {
+        let tonics = match self.mode {
+            Mode::Major => &MAJOR_TONICS,
+            Mode::Minor => &MINOR_TONICS,
         };
+        tonics[(self.sharps_flats + 7) as usize]
+    }
 
-        bytes.push(major_minor_bit);
+    /// Gets the pitch classes of the key's scale, starting from the tonic. Minor keys use the
+    /// natural minor scale
+    pub fn scale(&self) -> [u8; 7] {
+        let tonic = self.tonic();
+        self.mode
+            .intervals()
+            .map(|interval| (tonic + interval) % 12)
+    }
 
-        bytes
+    /// Gets the relative key, which shares this key's signature but not its mode
+    pub fn relative(&self) -> Self {
+        Self {
+            sharps_flats: self.sharps_flats,
+            mode: self.mode.other(),
+        }
+    }
+
+    /// Gets the parallel key, which shares this key's tonic but not its mode. Returns `None` if
+    /// it would need more than 7 sharps or flats
+    pub fn parallel(&self) -> Option<Self> {
+        let shift = match self.mode {
+            Mode::Major => -3,
+            Mode::Minor => 3,
+        };
+        Self::new(self.sharps_flats + shift, self.mode.other()).ok()
+    }
+}
+
+impl core::fmt::Display for KeySignature {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "{} {}", self.tonic_name(), self.mode]
+    }
+}
+
+/// Error type for parsing a key name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyParseError {
+    /// The name doesn't end with `major` or `minor`
+    InvalidMode,
+    /// The tonic isn't the tonic of a key with at most 7 sharps or flats in the given mode
+    UnknownTonic,
+}
+
+impl core::error::Error for KeyParseError {}
+impl core::fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidMode => write![f, "Key name doesn't end with major or minor"],
+            Self::UnknownTonic => write![f, "Key name has no key signature"],
+        }
+    }
+}
+
+impl core::str::FromStr for KeySignature {
+    type Err = KeyParseError;
+    /// Parses a key name such as `F# minor` or `bb major`. The tonic is case insensitive and may
+    /// use `♯` and `♭` for its accidental
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tonic, mode) = s.trim().split_once(' ').ok_or(KeyParseError::InvalidMode)?;
+        let (mode, tonics) = match mode.trim() {
+            mode if mode.eq_ignore_ascii_case("major") => (Mode::Major, &MAJOR_TONICS),
+            mode if mode.eq_ignore_ascii_case("minor") => (Mode::Minor, &MINOR_TONICS),
+            _ => return Err(KeyParseError::InvalidMode),
+        };
+
+        let mut chars = tonic.chars();
+        let letter = chars.next().ok_or(KeyParseError::UnknownTonic)?;
+        let accidental = match chars.as_str() {
+            "" => "",
+            "#" | "♯" => "#",
+            "b" | "♭" => "b",
+            _ => return Err(KeyParseError::UnknownTonic),
+        };
+
+        let index = tonics
+            .iter()
+            .position(|name| {
+                let (name_letter, name_accidental) = name.split_at(1);
+                name_accidental == accidental
+                    && name_letter.eq_ignore_ascii_case(letter.encode_utf8(&mut [0; 4]))
+            })
+            .ok_or(KeyParseError::UnknownTonic)?;
+
+        Ok(Self {
+            sharps_flats: index as i8 - 7,
+            mode,
+        })
+    }
+}
+
+impl MidiWriteable for KeySignature {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        let mode = match self.mode {
+            Mode::Major => 0,
+            Mode::Minor => 1,
+        };
+        vec![self.sharps_flats.to_be_bytes()[0], mode]
     }
 }
 
@@ -153,6 +338,51 @@ pub struct SmpteOffset {
     subframes: u8,
 }
 
+impl SmpteOffset {
+    /// Creates a new SMPTE offset, failing if any field is out of range for a time of day with
+    /// up to 30 frames per second and 100 subframes per frame
+    pub fn new(
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        subframes: u8,
+    ) -> Result<Self, OutOfRange> {
+        Ok(Self {
+            hours: OutOfRange::check("hours", hours, 0, 23)?,
+            minutes: OutOfRange::check("minutes", minutes, 0, 59)?,
+            seconds: OutOfRange::check("seconds", seconds, 0, 59)?,
+            frames: OutOfRange::check("frames", frames, 0, 29)?,
+            subframes: OutOfRange::check("subframes", subframes, 0, 99)?,
+        })
+    }
+
+    /// Gets the hours of offset
+    pub fn hours(&self) -> u8 {
+        self.hours
+    }
+
+    /// Gets the minutes of offset
+    pub fn minutes(&self) -> u8 {
+        self.minutes
+    }
+
+    /// Gets the seconds of offset
+    pub fn seconds(&self) -> u8 {
+        self.seconds
+    }
+
+    /// Gets the frames of offset
+    pub fn frames(&self) -> u8 {
+        self.frames
+    }
+
+    /// Gets the subframes of offset
+    pub fn subframes(&self) -> u8 {
+        self.subframes
+    }
+}
+
 impl MidiWriteable for SmpteOffset {
     fn to_midi_bytes(self) -> Vec<u8> {
         let SmpteOffset {
@@ -166,6 +396,68 @@ impl MidiWriteable for SmpteOffset {
     }
 }
 
+/// The denominator of a time signature, a power of two stored as its exponent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u32", into = "u32")
+)]
+pub struct Denominator(u8);
+
+impl Denominator {
+    /// A quarter note denominator
+    pub const QUARTER: Self = Self(2);
+    /// The largest exponent, which keeps the denominator within a `u32`
+    const MAX_EXPONENT: u8 = 31;
+
+    /// Creates a denominator, failing if it isn't a power of two
+    pub fn new(denominator: u32) -> Result<Self, OutOfRange> {
+        if !denominator.is_power_of_two() {
+            return Err(OutOfRange {
+                field: "time signature denominator",
+                value: denominator.into(),
+            });
+        }
+        Ok(Self(denominator.trailing_zeros() as u8))
+    }
+
+    /// Creates a denominator from the power of two it is, as stored in MIDI files, failing if it
+    /// is above 31
+    pub fn from_exponent(exponent: u8) -> Result<Self, OutOfRange> {
+        OutOfRange::check(
+            "time signature denominator exponent",
+            exponent,
+            0,
+            Self::MAX_EXPONENT,
+        )
+        .map(Self)
+    }
+
+    /// Gets the power of two the denominator is
+    pub fn exponent(self) -> u8 {
+        self.0
+    }
+
+    /// Gets the denominator
+    pub fn get(self) -> u32 {
+        1 << self.0
+    }
+}
+
+impl TryFrom<u32> for Denominator {
+    type Error = OutOfRange;
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<Denominator> for u32 {
+    fn from(value: Denominator) -> Self {
+        value.get()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// A Time Signature
@@ -173,26 +465,89 @@ pub struct TimeSignature {
     /// The time signature's numerator
     numerator: u8,
     /// The time signature's denominator
-    denominator: u32,
+    denominator: Denominator,
     /// Clocks per tick
     clocks_per_tick: u8,
     /// Thirty second notes per quarter
     thirty_second_notes_per_quarter: u8,
 }
 
-impl MidiWriteable for TimeSignature {
-    fn to_midi_bytes(self) -> Vec<u8> {
-        let TimeSignature {
-            numerator,
-            denominator,
+impl TimeSignature {
+    /// 4/4 time with a metronome click every quarter note
+    pub const COMMON_TIME: Self = Self {
+        numerator: 4,
+        denominator: Denominator::QUARTER,
+        clocks_per_tick: 24,
+        thirty_second_notes_per_quarter: 8,
+    };
+
+    /// Creates a new time signature, failing if the numerator is 0 or the denominator isn't a
+    /// power of two
+    pub fn new(
+        numerator: u8,
+        denominator: u32,
+        clocks_per_tick: u8,
+        thirty_second_notes_per_quarter: u8,
+    ) -> Result<Self, OutOfRange> {
+        Ok(Self {
+            numerator: OutOfRange::check("time signature numerator", numerator, 1, u8::MAX)?,
+            denominator: Denominator::new(denominator)?,
             clocks_per_tick,
             thirty_second_notes_per_quarter,
-        } = self;
-        let mut bytes = vec![numerator];
-        bytes.extend(denominator.to_midi_bytes().iter());
-        bytes.extend([clocks_per_tick, thirty_second_notes_per_quarter]);
+        })
+    }
 
-        bytes
+    /// Gets the time signature's numerator
+    pub fn numerator(&self) -> u8 {
+        self.numerator
+    }
+
+    /// Gets the time signature's denominator
+    pub fn denominator(&self) -> Denominator {
+        self.denominator
+    }
+
+    /// Gets the number of MIDI clocks per metronome click
+    pub fn clocks_per_tick(&self) -> u8 {
+        self.clocks_per_tick
+    }
+
+    /// Gets the number of notated 32nd notes in a MIDI quarter note
+    pub fn thirty_second_notes_per_quarter(&self) -> u8 {
+        self.thirty_second_notes_per_quarter
+    }
+
+    /// Gets the number of ticks in a beat, the note value of the denominator, rounding down.
+    /// Returns `None` for [`Division::TimeCodeBased`] files, whose ticks don't follow the beat
+    pub fn beat_ticks(&self, division: Division) -> Option<u64> {
+        self.ticks_for_beats(1, division)
+    }
+
+    /// Gets the number of ticks in a bar, rounding down. Returns `None` for
+    /// [`Division::TimeCodeBased`] files, whose ticks don't follow the beat
+    pub fn bar_ticks(&self, division: Division) -> Option<u64> {
+        self.ticks_for_beats(self.numerator as u64, division)
+    }
+
+    /// Gets the number of ticks in a number of beats, rounding down once at the end
+    fn ticks_for_beats(&self, beats: u64, division: Division) -> Option<u64> {
+        match division {
+            Division::Metrical(ticks_per_quarter) => {
+                Some((beats * ticks_per_quarter as u64 * 4) >> self.denominator.exponent())
+            }
+            Division::TimeCodeBased(_) => None,
+        }
+    }
+}
+
+impl MidiWriteable for TimeSignature {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        vec![
+            self.numerator,
+            self.denominator.exponent(),
+            self.clocks_per_tick,
+            self.thirty_second_notes_per_quarter,
+        ]
     }
 }
 
@@ -213,21 +568,56 @@ where
 
         let data = value.0.get(length as usize);
 
-        macro_rules! meta_event {
-            ($len: expr_2021, $name: expr_2021, $value: expr_2021) => {{
-                if data.len() != $len {
-                    return Err(TrackError::InvalidMetaEventData);
-                }
-                Ok($name($value))
-            }};
+        MetaEvent::try_from_data(event_tag, data)
+    }
+}
+
+impl MetaEvent {
+    /// Gets the exact payload length required by a meta event tag, if it has a fixed length
+    fn fixed_len(tag: u8) -> Option<usize> {
+        match tag {
+            0x00 => Some(2),
+            0x20 => Some(1),
+            0x51 => Some(3),
+            0x54 => Some(5),
+            0x58 => Some(4),
+            0x59 => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a meta event tag carries UTF-8 text
+    fn is_text(tag: u8) -> bool {
+        (0x01..=0x06).contains(&tag)
+    }
+
+    /// Checks that a meta event payload is valid for its tag without copying it, so that
+    /// [`MetaEvent::try_from_data`] is guaranteed to succeed on it. Out of range time and key
+    /// signatures are valid, as they are kept as [`MetaEvent::UnknownRaw`]
+    pub(crate) fn check_data(tag: u8, data: &[u8]) -> Result<(), TrackError> {
+        if MetaEvent::fixed_len(tag).is_some_and(|len| len != data.len()) {
+            return Err(TrackError::InvalidMetaEventData);
+        }
+        if MetaEvent::is_text(tag) && core::str::from_utf8(data).is_err() {
+            // Only build the owned error once the data is known to be invalid
+            return Err(String::from_utf8(data.to_vec()).unwrap_err().into());
+        }
+
+        Ok(())
+    }
+
+    /// Parses a meta event from its tag and payload. Time signatures with a denominator exponent
+    /// above 31 and key signatures outside -7 to 7 are kept as [`MetaEvent::UnknownRaw`] so they
+    /// are written back unchanged
+    pub(crate) fn try_from_data(event_tag: u8, data: Vec<u8>) -> Result<Self, TrackError> {
+        if MetaEvent::fixed_len(event_tag).is_some_and(|len| len != data.len()) {
+            return Err(TrackError::InvalidMetaEventData);
         }
 
         match event_tag {
-            0x00 => meta_event!(
-                2,
-                MetaEvent::SequenceNumber,
-                u16::from_be_bytes([data[0], data[1]])
-            ),
+            0x00 => Ok(MetaEvent::SequenceNumber(u16::from_be_bytes([
+                data[0], data[1],
+            ]))),
             0x01 => Ok(MetaEvent::Text(String::from_utf8(data)?)),
             0x02 => Ok(MetaEvent::Copyright(String::from_utf8(data)?)),
             0x03 => Ok(MetaEvent::TrackName(String::from_utf8(data)?)),
@@ -236,43 +626,39 @@ where
             0x06 => Ok(MetaEvent::Marker(String::from_utf8(data)?)),
             0x07 => Ok(MetaEvent::CuePoint(data)),
 
-            0x20 => meta_event!(1, MetaEvent::MidiChannelPrefix, data[0]),
+            0x20 => Ok(MetaEvent::MidiChannelPrefix(data[0])),
             0x2F => Ok(MetaEvent::EndOfTrack),
 
-            0x51 => meta_event!(
-                3,
-                MetaEvent::Tempo,
-                ((data[0] as u32) << 16) | ((data[1] as u32) << 8) | (data[2] as u32)
-            ),
-            0x54 => meta_event!(
-                5,
-                MetaEvent::SmpteOffset,
-                SmpteOffset {
-                    hours: data[0],
-                    minutes: data[1],
-                    seconds: data[2],
-                    frames: data[3],
-                    subframes: data[4]
-                }
-            ),
-            0x58 => meta_event!(
-                4,
-                MetaEvent::TimeSignature,
-                TimeSignature {
+            0x51 => Ok(MetaEvent::Tempo(
+                ((data[0] as u32) << 16) | ((data[1] as u32) << 8) | (data[2] as u32),
+            )),
+            0x54 => Ok(MetaEvent::SmpteOffset(SmpteOffset {
+                hours: data[0],
+                minutes: data[1],
+                seconds: data[2],
+                frames: data[3],
+                subframes: data[4],
+            })),
+            0x58 => match Denominator::from_exponent(data[1]) {
+                Ok(denominator) => Ok(MetaEvent::TimeSignature(TimeSignature {
                     numerator: data[0],
-                    denominator: 2u32.pow(data[1] as u32),
+                    denominator,
                     clocks_per_tick: data[2],
                     thirty_second_notes_per_quarter: data[3],
+                })),
+                Err(_) => Ok(MetaEvent::UnknownRaw(event_tag, data)),
+            },
+            0x59 => {
+                let mode = if data[1] == 0 {
+                    Mode::Major
+                } else {
+                    Mode::Minor
+                };
+                match KeySignature::new(data[0] as i8, mode) {
+                    Ok(key_signature) => Ok(MetaEvent::KeySignature(key_signature)),
+                    Err(_) => Ok(MetaEvent::UnknownRaw(event_tag, data)),
                 }
-            ),
-            0x59 => meta_event!(
-                2,
-                MetaEvent::KeySignature,
-                KeySignature {
-                    sharps_flats: data[0] as i8,
-                    major_minor: data[1] != 0
-                }
-            ),
+            }
 
             0x7F => Ok(MetaEvent::SequencerSpecific(data)),
 
@@ -284,10 +670,13 @@ where
 #[cfg(test)]
 mod tests {
     use crate::{
-        chunk::track::{
-            event::IteratorWrapper,
-            meta::{KeySignature, MetaEvent, SmpteOffset, TimeSignature},
-            TrackError,
+        chunk::{
+            header::Division,
+            track::{
+                event::IteratorWrapper,
+                meta::{Denominator, KeySignature, MetaEvent, Mode, SmpteOffset, TimeSignature},
+                TrackError,
+            },
         },
         writer::MidiWriteable,
     };
@@ -330,7 +719,7 @@ mod tests {
             result,
             MetaEvent::TimeSignature(TimeSignature {
                 numerator: 4,
-                denominator: 4, // 2^2 = 4
+                denominator: Denominator::QUARTER, // 2^2 = 4
                 clocks_per_tick: 24,
                 thirty_second_notes_per_quarter: 8,
             })
@@ -345,7 +734,7 @@ mod tests {
             result,
             MetaEvent::KeySignature(KeySignature {
                 sharps_flats: 0,
-                major_minor: false,
+                mode: Mode::Major,
             })
         );
     }
@@ -394,11 +783,22 @@ mod tests {
         assert_eq!(result, Err(TrackError::OutOfSpace));
     }
 
+    #[test]
+    fn meta_constructors_validate_ranges() {
+        assert!(KeySignature::new(-7, Mode::Minor).is_ok());
+        assert!(KeySignature::new(8, Mode::Major).is_err());
+        assert!(TimeSignature::new(6, 8, 24, 8).is_ok());
+        assert!(TimeSignature::new(3, 6, 24, 8).is_err());
+        assert!(TimeSignature::new(0, 4, 24, 8).is_err());
+        assert!(SmpteOffset::new(23, 59, 59, 29, 99).is_ok());
+        assert!(SmpteOffset::new(24, 0, 0, 0, 0).is_err());
+    }
+
     #[test]
     fn meta_event_backwards_parses_to_bytes() {
         let expected = MetaEvent::KeySignature(KeySignature {
             sharps_flats: 0,
-            major_minor: false,
+            mode: Mode::Major,
         });
 
         let bytes = expected.clone().to_midi_bytes();
@@ -407,6 +807,46 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn key_signatures_name_their_keys() {
+        let key: KeySignature = "F# minor".parse().unwrap();
+        assert_eq!(key, KeySignature::new(3, Mode::Minor).unwrap());
+        assert_eq!(key.to_string(), "F# minor");
+        assert_eq!(key.tonic(), 6);
+        assert_eq!(key.scale(), [6, 8, 9, 11, 1, 2, 4]);
+        assert_eq!(key.relative().to_string(), "A major");
+        assert_eq!(key.parallel().unwrap().to_string(), "F# major");
+
+        for sharps_flats in -7..=7 {
+            for mode in [Mode::Major, Mode::Minor] {
+                let key = KeySignature::new(sharps_flats, mode).unwrap();
+                assert_eq!(key.to_string().parse(), Ok(key));
+            }
+        }
+
+        assert_eq!(
+            "bb MAJOR".parse::<KeySignature>().unwrap().sharps_flats(),
+            -2
+        );
+        assert_eq!("Cb major".parse::<KeySignature>().unwrap().parallel(), None);
+        assert!("D# major".parse::<KeySignature>().is_err());
+        assert!("C dorian".parse::<KeySignature>().is_err());
+    }
+
+    #[test]
+    fn time_signatures_measure_bars_and_beats() {
+        assert!(Denominator::new(6).is_err());
+        assert_eq!(Denominator::new(8).map(Denominator::exponent), Ok(3));
+
+        let six_eight = TimeSignature::new(6, 8, 36, 8).unwrap();
+        assert_eq!(six_eight.denominator().get(), 8);
+        assert_eq!(six_eight.beat_ticks(Division::Metrical(480)), Some(240));
+        assert_eq!(six_eight.bar_ticks(Division::Metrical(480)), Some(1440));
+
+        let three_sixteen = TimeSignature::new(3, 16, 6, 8).unwrap();
+        assert_eq!(three_sixteen.bar_ticks(Division::Metrical(10)), Some(7));
+    }
+
     macro_rules! meta_event_test {
         ($name:ident, $event:expr_2021, $data:expr_2021) => {
             #[test]
@@ -499,11 +939,35 @@ mod tests {
         key_signature_event,
         MetaEvent::KeySignature(KeySignature {
             sharps_flats: 0,
-            major_minor: false,
+            mode: Mode::Major,
         }),
         vec![0xFF, 0x59, 0x02, 0x00, 0x00]
     );
 
+    meta_event_test!(
+        time_signature_event,
+        MetaEvent::TimeSignature(TimeSignature::new(6, 8, 36, 8).unwrap()),
+        vec![0xFF, 0x58, 0x04, 0x06, 0x03, 0x24, 0x08]
+    );
+
+    meta_event_test!(
+        minor_key_signature_event,
+        MetaEvent::KeySignature(KeySignature::new(-3, Mode::Minor).unwrap()),
+        vec![0xFF, 0x59, 0x02, 0xFD, 0x01]
+    );
+
+    meta_event_test!(
+        out_of_range_time_signature_event,
+        MetaEvent::UnknownRaw(0x58, vec![0x04, 0x40, 0x18, 0x08]),
+        vec![0xFF, 0x58, 0x04, 0x04, 0x40, 0x18, 0x08]
+    );
+
+    meta_event_test!(
+        out_of_range_key_signature_event,
+        MetaEvent::UnknownRaw(0x59, vec![0x08, 0x00]),
+        vec![0xFF, 0x59, 0x02, 0x08, 0x00]
+    );
+
     meta_event_test!(
         sequencer_specific_event,
         MetaEvent::SequencerSpecific(vec![0x01, 0x02, 0x03]),
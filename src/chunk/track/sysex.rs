@@ -1,33 +1,212 @@
 //! System Exclusive Messages
 
-use crate::writer::MidiWriteable;
+use alloc::vec::Vec;
+
+use crate::io::{self, Write};
+
+use crate::{
+    chunk::{track::MTrkEvent, OutOfRange},
+    reader::Yieldable,
+    writer::{write_vlq, MidiWriteable, WriteMidi, WriteOptions},
+};
 
 use super::{event::IteratorWrapper, TrackError};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-/// A midi system exclusize event message
+/// The byte that starts a system exclusive message
+const SYSEX_START: u8 = 0xF0;
+/// The byte that ends a system exclusive message, also used to start continuation and escape
+/// packets in a Standard MIDI File
+const SYSEX_END: u8 = 0xF7;
+
+/// A system exclusive event as stored in a Standard MIDI File. Every form is prefixed by a
+/// variable length quantity giving the number of bytes that follow
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SysexEvent {
+    /// `F0 <length> <bytes>`, a full system exclusive message or the first packet of a message
+    /// divided across several events
+    Message(SysexMessage),
+    /// `F7 <length> <bytes>`, a continuation packet of a divided system exclusive message
+    Continuation(SysexContinuation),
+    /// `F7 <length> <bytes>`, arbitrary bytes to be transmitted as-is, such as real-time or
+    /// system common messages
+    Escape(Vec<u8>),
+}
+
+impl MidiWriteable for SysexEvent {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
+
+impl WriteMidi for SysexEvent {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        match self {
+            Self::Message(message) => {
+                writer.write_all(&[SYSEX_START])?;
+                write_vlq(writer, message.midi_len(options) as u32)?;
+                message.write_midi(writer, options)
+            }
+            Self::Continuation(continuation) => {
+                writer.write_all(&[SYSEX_END])?;
+                write_vlq(writer, continuation.midi_len(options) as u32)?;
+                continuation.write_midi(writer, options)
+            }
+            Self::Escape(bytes) => {
+                writer.write_all(&[SYSEX_END])?;
+                write_vlq(writer, bytes.len() as u32)?;
+                writer.write_all(bytes)
+            }
+        }
+    }
+}
+
+/// A midi system exclusize message, or the first packet of a divided one
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct SysexEvent {
+pub struct SysexMessage {
     /// The manufacture ID of the System Exclusize message
     manufacture_id: ManufactureId,
-    /// Data payload to be parsed on a per-system basis
+    /// Data payload to be parsed on a per-system basis, excluding the terminating 0xF7
     payload: Vec<u8>,
+    /// True if the packet ends with 0xF7, false if continuation packets follow
+    complete: bool,
 }
 
-impl MidiWriteable for SysexEvent {
+impl SysexMessage {
+    /// Creates a complete system exclusive message, failing if any payload byte is above 127
+    pub fn new(manufacture_id: ManufactureId, payload: Vec<u8>) -> Result<Self, OutOfRange> {
+        check_payload(&payload)?;
+        Ok(Self {
+            manufacture_id,
+            payload,
+            complete: true,
+        })
+    }
+
+    /// Creates the first packet of a divided system exclusive message, to be followed by
+    /// [`SysexContinuation`] packets. Fails if any payload byte is above 127
+    pub fn new_divided(
+        manufacture_id: ManufactureId,
+        payload: Vec<u8>,
+    ) -> Result<Self, OutOfRange> {
+        check_payload(&payload)?;
+        Ok(Self {
+            manufacture_id,
+            payload,
+            complete: false,
+        })
+    }
+
+    /// Gets the message's manufacture ID
+    pub fn manufacture_id(&self) -> ManufactureId {
+        self.manufacture_id
+    }
+
+    /// Gets the message's payload, excluding the manufacture ID and the terminating 0xF7
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Returns true if the message is terminated, false if it is waiting on continuation packets
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Appends a continuation packet to a divided message, joining the packets into a single
+    /// message. The message becomes complete once the final continuation is appended
+    pub fn append(&mut self, continuation: SysexContinuation) {
+        self.payload.extend(continuation.payload);
+        self.complete = continuation.complete;
+    }
+}
+
+impl MidiWriteable for SysexMessage {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
+
+impl WriteMidi for SysexMessage {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        _options: WriteOptions,
+    ) -> io::Result<()> {
+        writer.write_all(&self.manufacture_id.to_midi_bytes())?;
+        writer.write_all(&self.payload)?;
+        if self.complete {
+            writer.write_all(&[SYSEX_END])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A continuation packet of a system exclusive message that was divided across several events
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SysexContinuation {
+    /// Data payload, excluding the terminating 0xF7
+    payload: Vec<u8>,
+    /// True if this is the final packet, ending with 0xF7
+    complete: bool,
+}
+
+impl SysexContinuation {
+    /// Creates a continuation packet, `complete` marks the final packet of the message. Fails
+    /// if any payload byte is above 127
+    pub fn new(payload: Vec<u8>, complete: bool) -> Result<Self, OutOfRange> {
+        check_payload(&payload)?;
+        Ok(Self { payload, complete })
+    }
+
+    /// Gets the packet's payload, excluding the terminating 0xF7
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Returns true if this is the final packet of the divided message
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+impl MidiWriteable for SysexContinuation {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let mut bytes = vec![0xF0];
-        bytes.extend(self.manufacture_id.to_midi_bytes());
-        bytes.extend(self.payload.iter());
-        bytes.push(0xF7);
+        self.to_midi_vec(WriteOptions::default())
+    }
+}
 
-        bytes
+impl WriteMidi for SysexContinuation {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        _options: WriteOptions,
+    ) -> io::Result<()> {
+        writer.write_all(&self.payload)?;
+        if self.complete {
+            writer.write_all(&[SYSEX_END])?;
+        }
+
+        Ok(())
     }
 }
 
+/// Checks that every byte of a system exclusive payload is a 7 bit data byte
+fn check_payload(payload: &[u8]) -> Result<(), OutOfRange> {
+    payload
+        .iter()
+        .try_for_each(|byte| OutOfRange::check("sysex data byte", *byte, 0, 0x7F).map(|_| ()))
+}
+
 /// A manufacturer's ID. Can be either a 1 byte variant or 3 bytes
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -74,28 +253,92 @@ where
     ITER: Iterator<Item = u8>,
 {
     type Error = TrackError;
-    fn try_from(mut value: IteratorWrapper<&mut ITER>) -> Result<Self, Self::Error> {
-        let prefix = value.0.next().ok_or(TrackError::OutOfSpace)?;
-        if prefix != 0xF0 {
+    fn try_from(value: IteratorWrapper<&mut ITER>) -> Result<Self, Self::Error> {
+        SysexEvent::try_from_divided(value.0, &mut false)
+    }
+}
+
+impl SysexEvent {
+    /// Parses a sysex event. `divided` tracks whether a divided message is waiting on
+    /// continuation packets: while it is set, `F7` packets are parsed as continuations, otherwise
+    /// they are parsed as escapes. It is updated to reflect the parsed event
+    pub fn try_from_divided<ITER: Iterator<Item = u8>>(
+        value: &mut ITER,
+        divided: &mut bool,
+    ) -> Result<Self, TrackError> {
+        let prefix = value.next().ok_or(TrackError::OutOfSpace)?;
+        if prefix != SYSEX_START && prefix != SYSEX_END {
             return Err(TrackError::InvalidSysExMessage);
         }
 
-        let manufacture_id = ManufactureId::try_from(&mut value)?;
-        let mut payload = vec![];
+        let length = MTrkEvent::try_get_delta_time(value).ok_or(TrackError::OutOfSpace)?;
+        let data = value.get(length as usize);
+        if data.len() != length as usize {
+            return Err(TrackError::OutOfSpace);
+        }
+
+        SysexEvent::try_from_data(prefix, data, divided)
+    }
+
+    /// Checks that a sysex payload is valid for its prefix byte without copying it, so that
+    /// [`SysexEvent::try_from_data`] is guaranteed to succeed on it. `divided` is updated the
+    /// same way
+    pub(crate) fn check_data(
+        prefix: u8,
+        data: &[u8],
+        divided: &mut bool,
+    ) -> Result<(), TrackError> {
+        if prefix == SYSEX_END && !*divided {
+            return Ok(());
+        }
+
+        let complete = data.last() == Some(&SYSEX_END);
+        *divided = !complete;
 
-        loop {
-            let byte = value.0.next().ok_or(TrackError::MissingEndOfExclusive)?;
-            if byte == 0xF7 {
-                break;
+        if prefix == SYSEX_START {
+            let payload = if complete {
+                &data[..data.len() - 1]
             } else {
-                payload.push(byte);
-            }
+                data
+            };
+            ManufactureId::try_from(&mut IteratorWrapper(&mut payload.iter().copied()))?;
         }
 
-        Ok(Self {
-            manufacture_id,
-            payload,
-        })
+        Ok(())
+    }
+
+    /// Parses a sysex event from its prefix byte and payload, see
+    /// [`SysexEvent::try_from_divided`]
+    pub(crate) fn try_from_data(
+        prefix: u8,
+        mut data: Vec<u8>,
+        divided: &mut bool,
+    ) -> Result<Self, TrackError> {
+        if prefix == SYSEX_END && !*divided {
+            return Ok(Self::Escape(data));
+        }
+
+        let complete = data.last() == Some(&SYSEX_END);
+        if complete {
+            data.pop();
+        }
+        *divided = !complete;
+
+        if prefix == SYSEX_START {
+            let mut data = data.into_iter();
+            let manufacture_id = ManufactureId::try_from(&mut IteratorWrapper(&mut data))?;
+
+            Ok(Self::Message(SysexMessage {
+                manufacture_id,
+                payload: data.collect(),
+                complete,
+            }))
+        } else {
+            Ok(Self::Continuation(SysexContinuation {
+                payload: data,
+                complete,
+            }))
+        }
     }
 }
 
@@ -106,7 +349,7 @@ mod tests {
         writer::MidiWriteable,
     };
 
-    use super::{ManufactureId, SysexEvent};
+    use super::{ManufactureId, SysexContinuation, SysexEvent, SysexMessage};
 
     #[test]
     fn one_byte_manufature_id() {
@@ -137,41 +380,123 @@ mod tests {
 
     #[test]
     fn sys_ex_message_valid_parse() {
-        let mut data = [0xF0, 0x01, 0xFF, 0x00, 0x21, 0xF7].into_iter();
+        let mut data = [0xF0, 0x05, 0x01, 0xFF, 0x00, 0x21, 0xF7].into_iter();
         let wrapper = IteratorWrapper(&mut data);
 
         let sysex = SysexEvent::try_from(wrapper).expect("Parse sysex message from bytes");
-        let expected = SysexEvent {
+        let expected = SysexEvent::Message(SysexMessage {
             manufacture_id: ManufactureId::OneByte(0x01),
             payload: vec![0xFF, 0x00, 0x21],
-        };
+            complete: true,
+        });
 
         assert_eq!(sysex, expected)
     }
 
     #[test]
     fn sys_ex_message_invalid_parse_failes() {
-        let mut data = [0xF0, 0x01, 0xFF, 0x00, 0x21].into_iter();
+        let mut data = [0xF0, 0x05, 0x01, 0xFF, 0x00, 0x21].into_iter();
         let wrapper = IteratorWrapper(&mut data);
 
         let sysex = SysexEvent::try_from(wrapper);
 
-        assert_eq!(sysex, Err(TrackError::MissingEndOfExclusive))
+        assert_eq!(sysex, Err(TrackError::OutOfSpace))
+    }
+
+    #[test]
+    fn sys_ex_lengths_past_the_end_fail() {
+        let mut data = [0xF0, 0xFF, 0xFF, 0xFF, 0x7F, 0x01, 0xF7].into_iter();
+
+        assert_eq!(
+            SysexEvent::try_from_divided(&mut data, &mut false),
+            Err(TrackError::OutOfSpace)
+        );
     }
 
     #[test]
     fn sys_ex_message_converted_serializes_to_bytes_properly() {
-        let mut data = [0xF0, 0x01, 0xFF, 0x00, 0x21, 0xF7].into_iter();
+        let bytes = vec![0xF0, 0x05, 0x01, 0xFF, 0x00, 0x21, 0xF7];
+        let mut data = bytes.clone().into_iter();
         let wrapper = IteratorWrapper(&mut data);
 
         let sysex = SysexEvent::try_from(wrapper).expect("Parse sysex message from bytes");
 
-        let expected = sysex.clone();
-        let mut bytes = sysex.to_midi_bytes().into_iter();
-        let wrapper = IteratorWrapper(&mut bytes);
+        assert_eq!(sysex.to_midi_bytes(), bytes)
+    }
 
-        let sysex = SysexEvent::try_from(wrapper).expect("Parse sysex message from bytes");
+    #[test]
+    fn divided_sys_ex_message_continues() {
+        let mut data = [
+            0xF0, 0x03, 0x43, 0x12, 0x00, // First packet, no terminator
+            0xF7, 0x02, 0x34, 0x56, // Continuation
+            0xF7, 0x02, 0x78, 0xF7, // Final continuation
+            0xF7, 0x01, 0xFA, // Escaped real-time start message
+        ]
+        .into_iter();
+        let mut divided = false;
+
+        let mut packets = vec![];
+        for _ in 0..4 {
+            packets.push(
+                SysexEvent::try_from_divided(&mut data, &mut divided)
+                    .expect("Parse sysex packet from bytes"),
+            );
+        }
 
-        assert_eq!(sysex, expected)
+        assert_eq!(
+            packets,
+            vec![
+                SysexEvent::Message(SysexMessage {
+                    manufacture_id: ManufactureId::OneByte(0x43),
+                    payload: vec![0x12, 0x00],
+                    complete: false,
+                }),
+                SysexEvent::Continuation(SysexContinuation {
+                    payload: vec![0x34, 0x56],
+                    complete: false,
+                }),
+                SysexEvent::Continuation(SysexContinuation {
+                    payload: vec![0x78],
+                    complete: true,
+                }),
+                SysexEvent::Escape(vec![0xFA]),
+            ]
+        );
+        assert!(!divided);
+
+        let serialized: Vec<u8> = packets
+            .into_iter()
+            .flat_map(MidiWriteable::to_midi_bytes)
+            .collect();
+        assert_eq!(
+            serialized,
+            vec![
+                0xF0, 0x03, 0x43, 0x12, 0x00, 0xF7, 0x02, 0x34, 0x56, 0xF7, 0x02, 0x78, 0xF7, 0xF7,
+                0x01, 0xFA,
+            ]
+        )
+    }
+
+    #[test]
+    fn sys_ex_constructors_validate_payload() {
+        assert!(SysexMessage::new(ManufactureId::OneByte(0x43), vec![0x10, 0x7F]).is_ok());
+        assert!(SysexMessage::new(ManufactureId::OneByte(0x43), vec![0xF7]).is_err());
+        assert!(SysexContinuation::new(vec![0x80], true).is_err());
+    }
+
+    #[test]
+    fn divided_sys_ex_message_joins() {
+        let mut message = SysexMessage {
+            manufacture_id: ManufactureId::OneByte(0x43),
+            payload: vec![0x12],
+            complete: false,
+        };
+        message.append(SysexContinuation {
+            payload: vec![0x34],
+            complete: true,
+        });
+
+        assert_eq!(message.payload(), &[0x12, 0x34]);
+        assert!(message.is_complete())
     }
 }
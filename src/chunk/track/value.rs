@@ -0,0 +1,234 @@
+//! Range checked integer types for the channel and data values carried by MIDI events. A value
+//! of one of these types always fits in the bits the wire format gives it, so it can be written
+//! out without corrupting neighbouring bytes
+
+use crate::chunk::OutOfRange;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A MIDI channel, in the range 0-15
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub struct Channel(u8);
+
+impl Channel {
+    /// The highest channel
+    pub const MAX: Self = Self(0x0F);
+
+    /// Creates a channel, failing if it is above 15
+    pub fn new(channel: u8) -> Result<Self, OutOfRange> {
+        OutOfRange::check("channel", channel, 0, Self::MAX.0).map(Self)
+    }
+
+    /// Creates a channel, clamping values above 15 to 15
+    pub const fn saturating(channel: u8) -> Self {
+        if channel > Self::MAX.0 {
+            Self::MAX
+        } else {
+            Self(channel)
+        }
+    }
+
+    /// Creates a channel from the low nibble of a byte, such as a status byte
+    pub(crate) const fn from_low_bits(byte: u8) -> Self {
+        Self(byte & Self::MAX.0)
+    }
+
+    /// Gets the channel number
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = OutOfRange;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<Channel> for u8 {
+    fn from(value: Channel) -> Self {
+        value.0
+    }
+}
+
+impl core::fmt::Display for Channel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "{}", self.0]
+    }
+}
+
+/// A 7 bit data value, in the range 0-127
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub struct U7(u8);
+
+impl U7 {
+    /// The smallest 7 bit value
+    pub const MIN: Self = Self(0);
+    /// The largest 7 bit value
+    pub const MAX: Self = Self(0x7F);
+
+    /// Creates a 7 bit value, failing if it is above 127
+    pub fn new(value: u8) -> Result<Self, OutOfRange> {
+        Self::checked("data byte", value)
+    }
+
+    /// Creates a 7 bit value, naming the field it is for in the error if it is above 127
+    pub(crate) fn checked(field: &'static str, value: u8) -> Result<Self, OutOfRange> {
+        OutOfRange::check(field, value, 0, Self::MAX.0).map(Self)
+    }
+
+    /// Creates a 7 bit value, clamping values above 127 to 127
+    pub const fn saturating(value: u8) -> Self {
+        if value > Self::MAX.0 {
+            Self::MAX
+        } else {
+            Self(value)
+        }
+    }
+
+    /// Gets the value
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for U7 {
+    type Error = OutOfRange;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<U7> for u8 {
+    fn from(value: U7) -> Self {
+        value.0
+    }
+}
+
+impl core::fmt::Display for U7 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "{}", self.0]
+    }
+}
+
+/// A 14 bit value sent as two 7 bit data bytes, in the range 0-16383
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u16", into = "u16")
+)]
+pub struct U14(u16);
+
+impl U14 {
+    /// The largest 14 bit value
+    pub const MAX: Self = Self(0x3FFF);
+    /// The center value, which is the resting position of the pitch wheel
+    pub const CENTER: Self = Self(0x2000);
+
+    /// Creates a 14 bit value, failing if it is above 16383
+    pub fn new(value: u16) -> Result<Self, OutOfRange> {
+        Self::checked("14 bit value", value)
+    }
+
+    /// Creates a 14 bit value, naming the field it is for in the error if it is above 16383
+    pub(crate) fn checked(field: &'static str, value: u16) -> Result<Self, OutOfRange> {
+        OutOfRange::check(field, value, 0, Self::MAX.0).map(Self)
+    }
+
+    /// Creates a 14 bit value, clamping values above 16383 to 16383
+    pub const fn saturating(value: u16) -> Self {
+        if value > Self::MAX.0 {
+            Self::MAX
+        } else {
+            Self(value)
+        }
+    }
+
+    /// Combines the least and most significant 7 bits, in the order they are sent on the wire
+    pub const fn from_lsb_msb(lsb: U7, msb: U7) -> Self {
+        Self(((msb.0 as u16) << 7) | lsb.0 as u16)
+    }
+
+    /// Gets the least significant 7 bits
+    pub const fn lsb(self) -> U7 {
+        U7((self.0 & 0x7F) as u8)
+    }
+
+    /// Gets the most significant 7 bits
+    pub const fn msb(self) -> U7 {
+        U7((self.0 >> 7) as u8)
+    }
+
+    /// Gets the value
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for U14 {
+    type Error = OutOfRange;
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<U14> for u16 {
+    fn from(value: U14) -> Self {
+        value.0
+    }
+}
+
+impl From<U7> for U14 {
+    fn from(value: U7) -> Self {
+        Self(value.0 as u16)
+    }
+}
+
+impl core::fmt::Display for U14 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "{}", self.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Channel, U14, U7};
+
+    #[test]
+    fn constructors_check_and_saturate() {
+        assert_eq!(Channel::new(15).map(Channel::get), Ok(15));
+        assert!(Channel::new(16).is_err());
+        assert_eq!(Channel::saturating(200), Channel::MAX);
+
+        assert_eq!(U7::new(127).map(U7::get), Ok(127));
+        assert!(U7::new(128).is_err());
+        assert_eq!(U7::saturating(0xFF), U7::MAX);
+
+        assert_eq!(U14::new(0x3FFF), Ok(U14::MAX));
+        assert!(U14::new(0x4000).is_err());
+        assert_eq!(U14::saturating(u16::MAX), U14::MAX);
+    }
+
+    #[test]
+    fn u14_splits_into_seven_bit_halves() {
+        let value = U14::new(0x1F85).unwrap();
+        assert_eq!(value.lsb().get(), 0x05);
+        assert_eq!(value.msb().get(), 0x3F);
+        assert_eq!(U14::from_lsb_msb(value.lsb(), value.msb()), value);
+        assert_eq!(U14::CENTER.lsb().get(), 0x00);
+        assert_eq!(U14::CENTER.msb().get(), 0x40);
+    }
+}
@@ -0,0 +1,704 @@
+//! Named control change controllers, pairing of 14 bit controller halves, and decoding of
+//! registered and non-registered parameter numbers from the control changes that carry them
+
+use alloc::vec::Vec;
+
+use crate::chunk::track::{
+    event::{ControlChange, MidiEvent},
+    value::{Channel, U14, U7},
+    Event, TrackChunk,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Defines the [`Controller`] enum along with its conversions to and from controller numbers
+macro_rules! controllers {
+    ($($(#[$doc:meta])* $name:ident = $number:literal,)*) => {
+        /// A control change's controller, named for the standard MIDI 1.0 assignments. Numbers
+        /// without a standard assignment are kept as [`Controller::Other`]. Named numbers should
+        /// be built with [`Controller::from_number`] so they compare equal to their named variant
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        pub enum Controller {
+            $($(#[$doc])* $name,)*
+            /// A controller without a standard assignment
+            Other(U7),
+        }
+
+        impl Controller {
+            /// Gets the controller with the given number
+            pub fn from_number(number: U7) -> Self {
+                match number.get() {
+                    $($number => Self::$name,)*
+                    _ => Self::Other(number),
+                }
+            }
+
+            /// Gets the controller's number
+            pub fn number(self) -> U7 {
+                match self {
+                    $(Self::$name => U7::saturating($number),)*
+                    Self::Other(number) => number,
+                }
+            }
+        }
+    };
+}
+
+controllers! {
+    /// Bank select, most significant 7 bits
+    BankSelect = 0,
+    /// Modulation wheel, most significant 7 bits
+    Modulation = 1,
+    /// Breath controller, most significant 7 bits
+    BreathController = 2,
+    /// Foot controller, most significant 7 bits
+    FootController = 4,
+    /// Portamento time, most significant 7 bits
+    PortamentoTime = 5,
+    /// Data entry for the selected parameter, most significant 7 bits
+    DataEntry = 6,
+    /// Channel volume, most significant 7 bits
+    Volume = 7,
+    /// Balance, most significant 7 bits
+    Balance = 8,
+    /// Pan, most significant 7 bits
+    Pan = 10,
+    /// Expression, most significant 7 bits
+    Expression = 11,
+    /// Effect control 1, most significant 7 bits
+    EffectControl1 = 12,
+    /// Effect control 2, most significant 7 bits
+    EffectControl2 = 13,
+    /// General purpose controller 1, most significant 7 bits
+    GeneralPurpose1 = 16,
+    /// General purpose controller 2, most significant 7 bits
+    GeneralPurpose2 = 17,
+    /// General purpose controller 3, most significant 7 bits
+    GeneralPurpose3 = 18,
+    /// General purpose controller 4, most significant 7 bits
+    GeneralPurpose4 = 19,
+    /// Bank select, least significant 7 bits
+    BankSelectLsb = 32,
+    /// Modulation wheel, least significant 7 bits
+    ModulationLsb = 33,
+    /// Breath controller, least significant 7 bits
+    BreathControllerLsb = 34,
+    /// Foot controller, least significant 7 bits
+    FootControllerLsb = 36,
+    /// Portamento time, least significant 7 bits
+    PortamentoTimeLsb = 37,
+    /// Data entry for the selected parameter, least significant 7 bits
+    DataEntryLsb = 38,
+    /// Channel volume, least significant 7 bits
+    VolumeLsb = 39,
+    /// Balance, least significant 7 bits
+    BalanceLsb = 40,
+    /// Pan, least significant 7 bits
+    PanLsb = 42,
+    /// Expression, least significant 7 bits
+    ExpressionLsb = 43,
+    /// Effect control 1, least significant 7 bits
+    EffectControl1Lsb = 44,
+    /// Effect control 2, least significant 7 bits
+    EffectControl2Lsb = 45,
+    /// General purpose controller 1, least significant 7 bits
+    GeneralPurpose1Lsb = 48,
+    /// General purpose controller 2, least significant 7 bits
+    GeneralPurpose2Lsb = 49,
+    /// General purpose controller 3, least significant 7 bits
+    GeneralPurpose3Lsb = 50,
+    /// General purpose controller 4, least significant 7 bits
+    GeneralPurpose4Lsb = 51,
+    /// Sustain (damper) pedal
+    Sustain = 64,
+    /// Portamento on or off
+    Portamento = 65,
+    /// Sostenuto pedal
+    Sostenuto = 66,
+    /// Soft pedal
+    SoftPedal = 67,
+    /// Legato footswitch
+    Legato = 68,
+    /// Hold 2
+    Hold2 = 69,
+    /// Sound controller 1, usually sound variation
+    SoundVariation = 70,
+    /// Sound controller 2, usually timbre or harmonic intensity
+    Timbre = 71,
+    /// Sound controller 3, usually release time
+    ReleaseTime = 72,
+    /// Sound controller 4, usually attack time
+    AttackTime = 73,
+    /// Sound controller 5, usually brightness
+    Brightness = 74,
+    /// Sound controller 6, usually decay time
+    DecayTime = 75,
+    /// Sound controller 7, usually vibrato rate
+    VibratoRate = 76,
+    /// Sound controller 8, usually vibrato depth
+    VibratoDepth = 77,
+    /// Sound controller 9, usually vibrato delay
+    VibratoDelay = 78,
+    /// Sound controller 10
+    SoundController10 = 79,
+    /// General purpose controller 5
+    GeneralPurpose5 = 80,
+    /// General purpose controller 6
+    GeneralPurpose6 = 81,
+    /// General purpose controller 7
+    GeneralPurpose7 = 82,
+    /// General purpose controller 8
+    GeneralPurpose8 = 83,
+    /// Portamento control, the key the next note glides from
+    PortamentoControl = 84,
+    /// Prefix extending the velocity of the next note with 7 more bits
+    HighResolutionVelocityPrefix = 88,
+    /// Effects 1 depth, usually reverb send
+    ReverbDepth = 91,
+    /// Effects 2 depth, usually tremolo
+    TremoloDepth = 92,
+    /// Effects 3 depth, usually chorus send
+    ChorusDepth = 93,
+    /// Effects 4 depth, usually detune or celeste
+    DetuneDepth = 94,
+    /// Effects 5 depth, usually phaser
+    PhaserDepth = 95,
+    /// Increments the selected parameter
+    DataIncrement = 96,
+    /// Decrements the selected parameter
+    DataDecrement = 97,
+    /// Non-registered parameter number, least significant 7 bits
+    NrpnLsb = 98,
+    /// Non-registered parameter number, most significant 7 bits
+    NrpnMsb = 99,
+    /// Registered parameter number, least significant 7 bits
+    RpnLsb = 100,
+    /// Registered parameter number, most significant 7 bits
+    RpnMsb = 101,
+    /// Silences every sounding note immediately, including release tails
+    AllSoundOff = 120,
+    /// Resets every controller to its default
+    ResetAllControllers = 121,
+    /// Connects or disconnects the instrument's keyboard from its sound generator
+    LocalControl = 122,
+    /// Releases every sounding note
+    AllNotesOff = 123,
+    /// Omni mode off
+    OmniOff = 124,
+    /// Omni mode on
+    OmniOn = 125,
+    /// Mono mode on
+    MonoOn = 126,
+    /// Poly mode on
+    PolyOn = 127,
+}
+
+impl Controller {
+    /// Whether this is a channel mode message rather than a controller, numbers 120 to 127
+    pub fn is_channel_mode(self) -> bool {
+        self.number().get() >= 120
+    }
+
+    /// Gets the controller carrying the least significant 7 bits of this one, for controllers 0
+    /// to 31
+    pub fn lsb_partner(self) -> Option<Self> {
+        let number = self.number().get();
+        (number < 32).then(|| Self::from_number(U7::saturating(number + 32)))
+    }
+
+    /// Gets the controller carrying the most significant 7 bits of this one, for controllers 32
+    /// to 63
+    pub fn msb_partner(self) -> Option<Self> {
+        let number = self.number().get();
+        (32..64)
+            .contains(&number)
+            .then(|| Self::from_number(U7::saturating(number - 32)))
+    }
+}
+
+impl From<U7> for Controller {
+    fn from(value: U7) -> Self {
+        Self::from_number(value)
+    }
+}
+
+impl From<Controller> for U7 {
+    fn from(value: Controller) -> Self {
+        value.number()
+    }
+}
+
+/// Which set of parameter numbers a [`ParameterChange`] belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ParameterKind {
+    /// Registered parameter numbers, selected with controllers 101 and 100
+    Rpn,
+    /// Non-registered parameter numbers, selected with controllers 99 and 98
+    Nrpn,
+}
+
+impl ParameterKind {
+    /// Gets the controllers selecting the most and least significant 7 bits of the number
+    fn select_controllers(self) -> (Controller, Controller) {
+        match self {
+            Self::Rpn => (Controller::RpnMsb, Controller::RpnLsb),
+            Self::Nrpn => (Controller::NrpnMsb, Controller::NrpnLsb),
+        }
+    }
+}
+
+/// A registered or non-registered parameter being set to a new value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParameterChange {
+    /// Whether the parameter is registered or not
+    pub kind: ParameterKind,
+    /// The parameter's number
+    pub number: U14,
+    /// The parameter's new value
+    pub value: U14,
+}
+
+impl ParameterChange {
+    /// The parameter number that deselects every parameter, so later data entry is ignored
+    pub const NULL: U14 = U14::MAX;
+
+    /// Registered parameter number for the pitch bend range, in semitones and cents
+    pub const PITCH_BEND_SENSITIVITY: U14 = U14::from_lsb_msb(U7::MIN, U7::MIN);
+    /// Registered parameter number for fine tuning, centered at 0x2000
+    pub const FINE_TUNING: U14 = U14::from_lsb_msb(U7::saturating(1), U7::MIN);
+    /// Registered parameter number for coarse tuning, in semitones centered at 0x40
+    pub const COARSE_TUNING: U14 = U14::from_lsb_msb(U7::saturating(2), U7::MIN);
+
+    /// Expands the change into the four control changes that make it up: the parameter number's
+    /// most and least significant 7 bits, followed by data entry's
+    pub fn to_control_changes(self) -> [ControlChange; 4] {
+        let (msb, lsb) = self.kind.select_controllers();
+        [
+            ControlChange::from_values(msb.number(), self.number.msb()),
+            ControlChange::from_values(lsb.number(), self.number.lsb()),
+            ControlChange::from_values(Controller::DataEntry.number(), self.value.msb()),
+            ControlChange::from_values(Controller::DataEntryLsb.number(), self.value.lsb()),
+        ]
+    }
+
+    /// Expands the change into control change events on a channel
+    pub fn to_events(self, channel: Channel) -> [MidiEvent; 4] {
+        self.to_control_changes()
+            .map(|change| MidiEvent::ControlChange(channel, change))
+    }
+}
+
+/// The parameter selection and data entry state of one channel
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ParameterState {
+    /// The kind of parameter most recently selected
+    selected: Option<ParameterKind>,
+    /// Most and least significant 7 bits of the selected registered parameter number
+    rpn: (Option<U7>, Option<U7>),
+    /// Most and least significant 7 bits of the selected non-registered parameter number
+    nrpn: (Option<U7>, Option<U7>),
+    /// The value last entered for the selected parameter
+    value: U14,
+}
+
+impl ParameterState {
+    /// Gets the selected parameter's kind and number, if both halves of the number were sent
+    /// and it isn't the null parameter
+    fn parameter(&self) -> Option<(ParameterKind, U14)> {
+        let kind = self.selected?;
+        let (msb, lsb) = match kind {
+            ParameterKind::Rpn => self.rpn,
+            ParameterKind::Nrpn => self.nrpn,
+        };
+        let number = U14::from_lsb_msb(lsb?, msb?);
+        (number != ParameterChange::NULL).then_some((kind, number))
+    }
+}
+
+/// Folds the control changes of a stream of events into [`ParameterChange`]s, keeping track of
+/// the selected parameter on each channel.
+///
+/// Data entry's most significant 7 bits reset the least significant ones to 0, following the
+/// MIDI 1.0 specification, so a change is reported for both halves when both are sent. Data
+/// increment and decrement step the whole 14 bit value by one. [`TrackChunk::parameter_changes`]
+/// merges the halves sent at the same tick into a single change
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterDecoder {
+    /// State of each channel
+    channels: [ParameterState; 16],
+}
+
+impl ParameterDecoder {
+    /// Creates a decoder with no parameters selected
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds an event to the decoder, returning the parameter change it completes, if any. Events
+    /// other than control changes are ignored
+    pub fn feed(&mut self, event: &MidiEvent) -> Option<ParameterChange> {
+        let MidiEvent::ControlChange(channel, change) = event else {
+            return None;
+        };
+        let state = &mut self.channels[channel.get() as usize];
+        let value = change.new_value();
+
+        let (part, kind) = match change.controller() {
+            Controller::RpnMsb => (&mut state.rpn.0, ParameterKind::Rpn),
+            Controller::RpnLsb => (&mut state.rpn.1, ParameterKind::Rpn),
+            Controller::NrpnMsb => (&mut state.nrpn.0, ParameterKind::Nrpn),
+            Controller::NrpnLsb => (&mut state.nrpn.1, ParameterKind::Nrpn),
+            controller => {
+                let (kind, number) = state.parameter()?;
+                state.value = match controller {
+                    Controller::DataEntry => U14::from_lsb_msb(U7::MIN, value),
+                    Controller::DataEntryLsb => U14::from_lsb_msb(value, state.value.msb()),
+                    Controller::DataIncrement => U14::saturating(state.value.get() + 1),
+                    Controller::DataDecrement => {
+                        U14::saturating(state.value.get().saturating_sub(1))
+                    }
+                    _ => return None,
+                };
+
+                return Some(ParameterChange {
+                    kind,
+                    number,
+                    value: state.value,
+                });
+            }
+        };
+
+        *part = Some(value);
+        state.selected = Some(kind);
+        state.value = U14::default();
+        None
+    }
+}
+
+/// A controller with an LSB partner being set to a 14 bit value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HighResolutionChange {
+    /// Channel the controller is changed on
+    pub channel: Channel,
+    /// The controller carrying the most significant 7 bits, numbered 0 to 31
+    pub controller: Controller,
+    /// The controller's new value
+    pub value: U14,
+}
+
+impl HighResolutionChange {
+    /// Expands the change into its control change events, the most significant 7 bits first so
+    /// the least significant ones aren't reset by it. Returns `None` if the controller has no LSB
+    /// partner
+    pub fn to_events(self) -> Option<[MidiEvent; 2]> {
+        let lsb = self.controller.lsb_partner()?;
+        Some(
+            [
+                ControlChange::from_values(self.controller.number(), self.value.msb()),
+                ControlChange::from_values(lsb.number(), self.value.lsb()),
+            ]
+            .map(|change| MidiEvent::ControlChange(self.channel, change)),
+        )
+    }
+}
+
+/// Pairs the control changes of controllers 0 to 31 with their LSB partners at 32 to 63,
+/// keeping track of each controller's 14 bit value on each channel.
+///
+/// Following the MIDI 1.0 specification, a change to the most significant 7 bits resets the
+/// least significant ones to 0, and a change to the least significant 7 bits keeps the most
+/// significant ones, so every change of either half is reported.
+/// [`TrackChunk::high_resolution_changes`] merges the halves sent at the same tick into a single
+/// change
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HighResolutionDecoder {
+    /// Current value of each channel's controllers 0 to 31
+    values: [[U14; 32]; 16],
+}
+
+impl HighResolutionDecoder {
+    /// Creates a decoder with every controller at 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds an event to the decoder, returning the 14 bit change it makes, if any. Events other
+    /// than control changes of controllers 0 to 63 are ignored
+    pub fn feed(&mut self, event: &MidiEvent) -> Option<HighResolutionChange> {
+        let MidiEvent::ControlChange(channel, change) = event else {
+            return None;
+        };
+        let controller = change.controller();
+        let number = controller.number().get();
+        let values = &mut self.values[channel.get() as usize];
+
+        let (controller, value) = if controller.lsb_partner().is_some() {
+            (controller, U14::from_lsb_msb(U7::MIN, change.new_value()))
+        } else {
+            let controller = controller.msb_partner()?;
+            let msb = values[number as usize - 32].msb();
+            (controller, U14::from_lsb_msb(change.new_value(), msb))
+        };
+
+        values[controller.number().get() as usize] = value;
+        Some(HighResolutionChange {
+            channel: *channel,
+            controller,
+            value,
+        })
+    }
+}
+
+impl TrackChunk {
+    /// Folds the track's control changes into parameter changes, along with the absolute tick
+    /// each change happens at. A data entry MSB followed by its LSB at the same tick is reported
+    /// once, with the value they set together
+    pub fn parameter_changes(&self) -> Vec<(u64, ParameterChange)> {
+        let mut decoder = ParameterDecoder::new();
+        let mut changes: Vec<(u64, ParameterChange)> = Vec::new();
+        // Index of each channel's latest change that only had its data entry MSB sent
+        let mut unsettled: [Option<usize>; 16] = [None; 16];
+
+        for (tick, event) in self.absolute_events() {
+            let Event::MidiEvent(event @ MidiEvent::ControlChange(channel, control_change)) = event
+            else {
+                continue;
+            };
+            let Some(change) = decoder.feed(event) else {
+                continue;
+            };
+
+            let slot = &mut unsettled[channel.get() as usize];
+            match (slot.take(), control_change.controller()) {
+                (Some(index), Controller::DataEntryLsb)
+                    if changes[index].0 == tick
+                        && changes[index].1.kind == change.kind
+                        && changes[index].1.number == change.number =>
+                {
+                    changes[index].1 = change;
+                }
+                (_, controller) => {
+                    if controller == Controller::DataEntry {
+                        *slot = Some(changes.len());
+                    }
+                    changes.push((tick, change));
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Pairs the track's controller MSB and LSB control changes into 14 bit values, along with
+    /// the absolute tick each change happens at. An MSB followed by its LSB at the same tick is
+    /// reported once, with the value they set together
+    pub fn high_resolution_changes(&self) -> Vec<(u64, HighResolutionChange)> {
+        let mut decoder = HighResolutionDecoder::new();
+        let mut changes: Vec<(u64, HighResolutionChange)> = Vec::new();
+        // Index of each channel's controllers' latest change that only had its MSB sent
+        let mut unsettled: [[Option<usize>; 32]; 16] = [[None; 32]; 16];
+
+        for (tick, event) in self.absolute_events() {
+            let Event::MidiEvent(event @ MidiEvent::ControlChange(_, control_change)) = event
+            else {
+                continue;
+            };
+            let Some(change) = decoder.feed(event) else {
+                continue;
+            };
+
+            let is_msb = control_change.controller().lsb_partner().is_some();
+            let slot = &mut unsettled[change.channel.get() as usize]
+                [change.controller.number().get() as usize];
+            match slot.take() {
+                Some(index) if !is_msb && changes[index].0 == tick => changes[index].1 = change,
+                _ => {
+                    if is_msb {
+                        *slot = Some(changes.len());
+                    }
+                    changes.push((tick, change));
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use crate::chunk::track::{
+        event::{ControlChange, MidiEvent},
+        value::{Channel, U14, U7},
+        Event, TrackChunk,
+    };
+
+    use super::{
+        Controller, HighResolutionChange, ParameterChange, ParameterDecoder, ParameterKind,
+    };
+
+    /// Builds a control change event on channel 0
+    fn cc(controller: Controller, value: u8) -> MidiEvent {
+        MidiEvent::ControlChange(
+            Channel::default(),
+            ControlChange::from_values(controller.number(), U7::new(value).unwrap()),
+        )
+    }
+
+    #[test]
+    fn controller_numbers_round_trip() {
+        for number in 0..=0x7F {
+            let number = U7::new(number).unwrap();
+            assert_eq!(Controller::from_number(number).number(), number);
+        }
+        assert_eq!(
+            Controller::from_number(U7::new(7).unwrap()),
+            Controller::Volume
+        );
+        assert_eq!(
+            Controller::from_number(U7::new(3).unwrap()),
+            Controller::Other(U7::new(3).unwrap())
+        );
+        assert!(Controller::AllNotesOff.is_channel_mode());
+        assert!(!Controller::Sustain.is_channel_mode());
+    }
+
+    #[test]
+    fn decoder_folds_data_entry_into_parameter_changes() {
+        let mut decoder = ParameterDecoder::new();
+        let events = [
+            cc(Controller::RpnMsb, 0),
+            cc(Controller::RpnLsb, 0),
+            cc(Controller::DataEntry, 12),
+            cc(Controller::DataEntryLsb, 50),
+            cc(Controller::DataIncrement, 0),
+            cc(Controller::Volume, 100),
+            cc(Controller::RpnMsb, 0x7F),
+            cc(Controller::RpnLsb, 0x7F),
+            cc(Controller::DataEntry, 1),
+        ];
+
+        let changes: Vec<_> = events
+            .iter()
+            .filter_map(|event| decoder.feed(event))
+            .collect();
+
+        let rpn = |value| ParameterChange {
+            kind: ParameterKind::Rpn,
+            number: ParameterChange::PITCH_BEND_SENSITIVITY,
+            value: U14::new(value).unwrap(),
+        };
+        assert_eq!(
+            changes,
+            [rpn(12 << 7), rpn((12 << 7) | 50), rpn((12 << 7) | 51)]
+        );
+    }
+
+    #[test]
+    fn encoder_output_decodes_to_the_same_change() {
+        let change = ParameterChange {
+            kind: ParameterKind::Nrpn,
+            number: U14::new(0x1234).unwrap(),
+            value: U14::new(0x2345).unwrap(),
+        };
+        let channel = Channel::new(5).unwrap();
+
+        let mut events: Vec<_> = change
+            .to_events(channel)
+            .into_iter()
+            .map(|event| (0, event.into()))
+            .collect();
+        let data_entry =
+            ControlChange::from_values(Controller::DataEntry.number(), U7::new(0x10).unwrap());
+        events.push((96, MidiEvent::ControlChange(channel, data_entry).into()));
+        let track = TrackChunk::from_absolute_events(events).unwrap();
+
+        let decoded = track.parameter_changes();
+        assert_eq!(
+            decoded,
+            vec![
+                (0, change),
+                (
+                    96,
+                    ParameterChange {
+                        kind: ParameterKind::Nrpn,
+                        number: U14::new(0x1234).unwrap(),
+                        value: U14::new(0x10 << 7).unwrap(),
+                    }
+                )
+            ]
+        );
+
+        let reencoded = decoded[0].1.to_events(channel).map(Event::from);
+        let original: Vec<_> = track.events()[..4]
+            .iter()
+            .map(|mtrk_event| mtrk_event.event().clone())
+            .collect();
+        assert_eq!(reencoded.to_vec(), original);
+
+        let mut decoder = ParameterDecoder::new();
+        for event in change.to_events(channel) {
+            decoder.feed(&event);
+        }
+        let other_channel = MidiEvent::ControlChange(
+            Channel::default(),
+            ControlChange::from_values(Controller::DataEntry.number(), U7::MAX),
+        );
+        assert_eq!(decoder.feed(&other_channel), None);
+    }
+
+    #[test]
+    fn msb_and_lsb_pair_into_fourteen_bit_values() {
+        assert_eq!(
+            Controller::Volume.lsb_partner(),
+            Some(Controller::VolumeLsb)
+        );
+        assert_eq!(
+            Controller::VolumeLsb.msb_partner(),
+            Some(Controller::Volume)
+        );
+        assert_eq!(Controller::Sustain.lsb_partner(), None);
+
+        let change = HighResolutionChange {
+            channel: Channel::default(),
+            controller: Controller::Modulation,
+            value: U14::new(0x1234).unwrap(),
+        };
+        let mut events: Vec<_> = change
+            .to_events()
+            .unwrap()
+            .into_iter()
+            .map(|event| (0, event.into()))
+            .collect();
+        events.push((96, cc(Controller::Modulation, 0x30).into()));
+        events.push((96, cc(Controller::Sustain, 0x7F).into()));
+        events.push((192, cc(Controller::ModulationLsb, 0x05).into()));
+
+        let track = TrackChunk::from_absolute_events(events).unwrap();
+        let decoded = track.high_resolution_changes();
+        let values: Vec<_> = decoded
+            .iter()
+            .map(|(tick, change)| (*tick, change.value.get()))
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![(0, 0x1234), (96, 0x30 << 7), (192, (0x30 << 7) | 0x05)]
+        );
+        assert_eq!(decoded[0].1, change);
+
+        let reencoded = decoded[0].1.to_events().unwrap().map(Event::from);
+        let original: Vec<_> = track.events()[..2]
+            .iter()
+            .map(|mtrk_event| mtrk_event.event().clone())
+            .collect();
+        assert_eq!(reencoded.to_vec(), original);
+    }
+}
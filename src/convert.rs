@@ -0,0 +1,399 @@
+//! Conversions between the three Standard MIDI File formats
+
+use alloc::{collections::BTreeMap, format, vec, vec::Vec};
+
+use crate::{
+    chunk::{
+        header::{Format, HeaderChunk},
+        track::{meta::MetaEvent, value::Channel, Event, TrackChunk},
+        OutOfRange, UnknownChunk,
+    },
+    Midi,
+};
+
+impl Midi {
+    /// Converts the file to format 0 by merging every track into a single track, ordered as in
+    /// [`Midi::merged_events`]. Format 2 patterns are first laid out one after another as in
+    /// [`Midi::into_format_one`]. A single `EndOfTrack` is placed at the end of the merged track.
+    /// Fails if laying out format 2 patterns leaves a gap too long for a delta time
+    pub fn into_format_zero(self) -> Result<Midi, OutOfRange> {
+        let midi = match self.header.format() {
+            Format::Two => self.into_format_one()?,
+            _ => self,
+        };
+
+        let mut end_tick = 0;
+        let mut events = vec![];
+        for merged in midi.merged_events() {
+            end_tick = end_tick.max(merged.tick);
+            if !matches!(merged.event, Event::MetaEvent(MetaEvent::EndOfTrack)) {
+                events.push((merged.tick, merged.event.clone()));
+            }
+        }
+        events.push((end_tick, Event::MetaEvent(MetaEvent::EndOfTrack)));
+
+        Ok(Midi {
+            header: HeaderChunk::from_parts(Format::Zero, 1, midi.header.division()),
+            tracks: vec![TrackChunk::from_absolute_events(events)?],
+            unknown_chunks: clamp_positions(midi.unknown_chunks, 1),
+        })
+    }
+
+    /// Converts the file to format 1.
+    ///
+    /// - Format 0 files are split by MIDI channel into one track per channel, ordered by channel.
+    ///   A conductor track is placed first, holding tempo, time signature and every other meta
+    ///   or sysex event that isn't tied to a channel, including the original track name. Meta
+    ///   events following a `MidiChannelPrefix` go to that channel's track, until the next
+    ///   channel event. Prefixes naming a channel above 15 are left on the conductor track. Channel
+    ///   tracks without a name of their own are given the original track name, or `Channel N`
+    ///   counting from 1 if the original track has none.
+    /// - Format 2 files have their patterns laid out one after another in time, each pattern
+    ///   starting where the previous one ended.
+    /// - Format 1 files are returned unchanged.
+    ///
+    /// Fails if a split or laid out track has consecutive events too far apart to encode as a
+    /// delta time
+    pub fn into_format_one(self) -> Result<Midi, OutOfRange> {
+        match self.header.format() {
+            Format::Zero => self.split_channels(),
+            Format::One => Ok(self),
+            Format::Two => self.sequence_patterns(),
+        }
+    }
+
+    /// Splits every track's events into a conductor track and one track per MIDI channel
+    fn split_channels(self) -> Result<Midi, OutOfRange> {
+        let mut end_tick = 0;
+        let mut name = None;
+        let mut conductor = vec![];
+        let mut channels: BTreeMap<u8, Vec<(u64, Event)>> = BTreeMap::new();
+
+        // Each track is walked in its own order so a channel prefix only covers the events that
+        // follow it in the same track
+        for track in &self.tracks {
+            let mut channel_prefix = None;
+
+            for (tick, event) in track.absolute_events() {
+                end_tick = end_tick.max(tick);
+
+                match event {
+                    Event::MidiEvent(midi_event) => {
+                        channel_prefix = None;
+                        channels
+                            .entry(midi_event.channel().get())
+                            .or_default()
+                            .push((tick, event.clone()));
+                    }
+                    Event::MetaEvent(MetaEvent::EndOfTrack) => {}
+                    Event::MetaEvent(MetaEvent::MidiChannelPrefix(channel))
+                        if Channel::new(*channel).is_ok() =>
+                    {
+                        channel_prefix = Some(*channel);
+                        channels
+                            .entry(*channel)
+                            .or_default()
+                            .push((tick, event.clone()));
+                    }
+                    // A prefix naming a channel above 15 ends any earlier prefix, and is kept
+                    // on the conductor track
+                    Event::MetaEvent(MetaEvent::MidiChannelPrefix(_)) => {
+                        channel_prefix = None;
+                        conductor.push((tick, event.clone()));
+                    }
+                    Event::MetaEvent(meta) => match channel_prefix {
+                        Some(channel) => channels
+                            .entry(channel)
+                            .or_default()
+                            .push((tick, event.clone())),
+                        None => {
+                            if let MetaEvent::TrackName(track_name) = meta {
+                                name.get_or_insert_with(|| track_name.clone());
+                            }
+                            conductor.push((tick, event.clone()))
+                        }
+                    },
+                    Event::SysexEvent(_) => conductor.push((tick, event.clone())),
+                }
+            }
+        }
+
+        for (channel, events) in &mut channels {
+            let named = events
+                .iter()
+                .any(|(_, event)| matches!(event, Event::MetaEvent(MetaEvent::TrackName(_))));
+            if !named {
+                let track_name = match &name {
+                    Some(name) => name.clone(),
+                    None => format!("Channel {}", u16::from(*channel) + 1),
+                };
+                events.insert(0, (0, Event::MetaEvent(MetaEvent::TrackName(track_name))));
+            }
+        }
+
+        let mut tracks = vec![conductor];
+        tracks.extend(channels.into_values());
+
+        let tracks = tracks
+            .into_iter()
+            .map(|mut events| {
+                events.push((end_tick, Event::MetaEvent(MetaEvent::EndOfTrack)));
+                TrackChunk::from_absolute_events(events)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Midi {
+            header: HeaderChunk::from_parts(
+                Format::One,
+                tracks.len() as u16,
+                self.header.division(),
+            ),
+            unknown_chunks: clamp_positions(self.unknown_chunks, tracks.len()),
+            tracks,
+        })
+    }
+
+    /// Offsets every pattern to start at the end of the previous one
+    fn sequence_patterns(self) -> Result<Midi, OutOfRange> {
+        let mut offset = 0;
+        let mut tracks = Vec::with_capacity(self.tracks.len());
+
+        for track in &self.tracks {
+            let mut end_tick = 0;
+            let events: Vec<_> = track
+                .absolute_events()
+                .map(|(tick, event)| {
+                    end_tick = tick;
+                    (tick + offset, event.clone())
+                })
+                .collect();
+
+            tracks.push(TrackChunk::from_absolute_events(events)?);
+            offset += end_tick;
+        }
+
+        Ok(Midi {
+            header: HeaderChunk::from_parts(
+                Format::One,
+                tracks.len() as u16,
+                self.header.division(),
+            ),
+            tracks,
+            unknown_chunks: self.unknown_chunks,
+        })
+    }
+}
+
+/// Keeps unknown chunks in their relative order while making sure none are placed after more
+/// tracks than a converted file has
+fn clamp_positions(unknown_chunks: Vec<UnknownChunk>, tracks: usize) -> Vec<UnknownChunk> {
+    unknown_chunks
+        .into_iter()
+        .map(|chunk| UnknownChunk {
+            position: chunk.position.min(tracks),
+            ..chunk
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk::{
+            header::{Format, HeaderChunk},
+            track::{meta::MetaEvent, Event},
+        },
+        tests::midi_from_tracks,
+        writer::MidiWriteable,
+    };
+
+    #[test]
+    fn format_one_merges_into_format_zero() {
+        let first: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Tempo at 0
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 0
+        ];
+        let second: &[u8] = &[
+            0x00, 0x90, 0x3C, 0x40, // Note on at 0
+            0x60, 0x80, 0x3C, 0x40, // Note off at 96
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 96
+        ];
+        let midi = midi_from_tracks(96, &[first, second])
+            .into_format_zero()
+            .unwrap();
+
+        assert_eq!(midi.header.format(), Format::Zero);
+        assert_eq!(midi.header.ntrks(), 1);
+        assert_eq!(midi.tracks.len(), 1);
+        assert_eq!(
+            midi.tracks[0].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, //
+                0x00, 0x90, 0x3C, 0x40, //
+                0x60, 0x80, 0x3C, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        )
+    }
+
+    #[test]
+    fn format_zero_splits_by_channel() {
+        let track: &[u8] = &[
+            0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', // Track name
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Tempo
+            0x00, 0x91, 0x3C, 0x40, // Note on, channel 1
+            0x00, 0x90, 0x40, 0x40, // Note on, channel 0
+            0x60, 0x81, 0x3C, 0x40, // Note off, channel 1
+            0x00, 0x80, 0x40, 0x40, // Note off, channel 0
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let mut midi = midi_from_tracks(96, &[track]);
+        midi.header = HeaderChunk::from_parts(Format::Zero, 1, midi.header.division());
+
+        let midi = midi.into_format_one().unwrap();
+
+        assert_eq!(midi.header.format(), Format::One);
+        assert_eq!(midi.header.ntrks(), 3);
+        assert_eq!(
+            midi.tracks[0].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, //
+                0x60, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(
+            midi.tracks[1].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x00, 0x90, 0x40, 0x40, //
+                0x60, 0x80, 0x40, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(
+            midi.tracks[2].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x00, 0x91, 0x3C, 0x40, //
+                0x60, 0x81, 0x3C, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        )
+    }
+
+    #[test]
+    fn channel_prefix_ends_at_the_next_channel_event() {
+        let track: &[u8] = &[
+            0x00, 0xFF, 0x20, 0x01, 0x01, // Channel prefix 1
+            0x00, 0x91, 0x3C, 0x40, // Note on, channel 1
+            0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', // Track name
+            0x60, 0x81, 0x3C, 0x40, // Note off, channel 1
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let mut midi = midi_from_tracks(96, &[track]);
+        midi.header = HeaderChunk::from_parts(Format::Zero, 1, midi.header.division());
+
+        let midi = midi.into_format_one().unwrap();
+
+        assert_eq!(midi.tracks.len(), 2);
+        assert_eq!(
+            midi.tracks[0].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x60, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(
+            midi.tracks[1].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x00, 0xFF, 0x20, 0x01, 0x01, //
+                0x00, 0x91, 0x3C, 0x40, //
+                0x60, 0x81, 0x3C, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        )
+    }
+
+    #[test]
+    fn out_of_range_channel_prefixes_are_ignored() {
+        let track: &[u8] = &[
+            0x00, 0xFF, 0x20, 0x01, 0x01, // Channel prefix 1
+            0x00, 0xFF, 0x20, 0x01, 0xFF, // Channel prefix 255
+            0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', // Track name
+            0x00, 0x91, 0x3C, 0x40, // Note on, channel 1
+            0x60, 0x81, 0x3C, 0x40, // Note off, channel 1
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let mut midi = midi_from_tracks(96, &[track]);
+        midi.header = HeaderChunk::from_parts(Format::Zero, 1, midi.header.division());
+
+        let midi = midi.into_format_one().unwrap();
+
+        assert_eq!(midi.tracks.len(), 2);
+        assert_eq!(
+            midi.tracks[0].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x20, 0x01, 0xFF, //
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x60, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(
+            midi.tracks[1].clone().to_midi_bytes(),
+            vec![
+                0x00, 0xFF, 0x03, 0x04, b'S', b'o', b'n', b'g', //
+                0x00, 0xFF, 0x20, 0x01, 0x01, //
+                0x00, 0x91, 0x3C, 0x40, //
+                0x60, 0x81, 0x3C, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        )
+    }
+
+    #[test]
+    fn unnamed_channel_tracks_are_named_after_their_channel() {
+        let track: &[u8] = &[
+            0x00, 0x92, 0x3C, 0x40, // Note on, channel 2
+            0x60, 0x82, 0x3C, 0x40, // Note off, channel 2
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let mut midi = midi_from_tracks(96, &[track]);
+        midi.header = HeaderChunk::from_parts(Format::Zero, 1, midi.header.division());
+
+        let midi = midi.into_format_one().unwrap();
+
+        assert_eq!(
+            midi.tracks[1].absolute_events().next(),
+            Some((
+                0,
+                &Event::MetaEvent(MetaEvent::TrackName("Channel 3".to_string()))
+            ))
+        )
+    }
+
+    #[test]
+    fn format_two_patterns_are_sequenced() {
+        let pattern: &[u8] = &[
+            0x00, 0x90, 0x3C, 0x40, // Note on at 0
+            0x60, 0x80, 0x3C, 0x40, // Note off at 96
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 96
+        ];
+        let mut midi = midi_from_tracks(96, &[pattern, pattern]);
+        midi.header = HeaderChunk::from_parts(Format::Two, 2, midi.header.division());
+
+        let midi = midi.into_format_one().unwrap();
+        let ticks: Vec<_> = midi.tracks[1]
+            .absolute_events()
+            .map(|(tick, _)| tick)
+            .collect();
+
+        assert_eq!(midi.header.format(), Format::One);
+        assert_eq!(ticks, vec![96, 192, 192]);
+
+        let merged = midi.into_format_zero().unwrap();
+        let last = merged.tracks[0].absolute_events().last();
+        assert_eq!(last, Some((192, &Event::MetaEvent(MetaEvent::EndOfTrack))))
+    }
+}
@@ -0,0 +1,812 @@
+//! General MIDI instrument, drum kit and percussion names, and the [`InstrumentMap`] that tracks
+//! which instrument each channel plays over time
+
+use alloc::vec::Vec;
+
+use crate::{
+    chunk::track::{
+        event::MidiEvent,
+        value::{Channel, U14, U7},
+        Event,
+    },
+    controller::Controller,
+    Midi,
+};
+
+/// The channel General MIDI reserves for percussion, channel 10 when counting from 1
+pub const PERCUSSION_CHANNEL: Channel = Channel::saturating(9);
+
+/// General MIDI Level 1 instrument names, indexed by program number
+pub const GM_INSTRUMENTS: [&str; 128] = [
+    "Acoustic Grand Piano",
+    "Bright Acoustic Piano",
+    "Electric Grand Piano",
+    "Honky-tonk Piano",
+    "Electric Piano 1",
+    "Electric Piano 2",
+    "Harpsichord",
+    "Clavi",
+    "Celesta",
+    "Glockenspiel",
+    "Music Box",
+    "Vibraphone",
+    "Marimba",
+    "Xylophone",
+    "Tubular Bells",
+    "Dulcimer",
+    "Drawbar Organ",
+    "Percussive Organ",
+    "Rock Organ",
+    "Church Organ",
+    "Reed Organ",
+    "Accordion",
+    "Harmonica",
+    "Tango Accordion",
+    "Acoustic Guitar (nylon)",
+    "Acoustic Guitar (steel)",
+    "Electric Guitar (jazz)",
+    "Electric Guitar (clean)",
+    "Electric Guitar (muted)",
+    "Overdriven Guitar",
+    "Distortion Guitar",
+    "Guitar Harmonics",
+    "Acoustic Bass",
+    "Electric Bass (finger)",
+    "Electric Bass (pick)",
+    "Fretless Bass",
+    "Slap Bass 1",
+    "Slap Bass 2",
+    "Synth Bass 1",
+    "Synth Bass 2",
+    "Violin",
+    "Viola",
+    "Cello",
+    "Contrabass",
+    "Tremolo Strings",
+    "Pizzicato Strings",
+    "Orchestral Harp",
+    "Timpani",
+    "String Ensemble 1",
+    "String Ensemble 2",
+    "Synth Strings 1",
+    "Synth Strings 2",
+    "Choir Aahs",
+    "Voice Oohs",
+    "Synth Voice",
+    "Orchestra Hit",
+    "Trumpet",
+    "Trombone",
+    "Tuba",
+    "Muted Trumpet",
+    "French Horn",
+    "Brass Section",
+    "Synth Brass 1",
+    "Synth Brass 2",
+    "Soprano Sax",
+    "Alto Sax",
+    "Tenor Sax",
+    "Baritone Sax",
+    "Oboe",
+    "English Horn",
+    "Bassoon",
+    "Clarinet",
+    "Piccolo",
+    "Flute",
+    "Recorder",
+    "Pan Flute",
+    "Blown Bottle",
+    "Shakuhachi",
+    "Whistle",
+    "Ocarina",
+    "Lead 1 (square)",
+    "Lead 2 (sawtooth)",
+    "Lead 3 (calliope)",
+    "Lead 4 (chiff)",
+    "Lead 5 (charang)",
+    "Lead 6 (voice)",
+    "Lead 7 (fifths)",
+    "Lead 8 (bass + lead)",
+    "Pad 1 (new age)",
+    "Pad 2 (warm)",
+    "Pad 3 (polysynth)",
+    "Pad 4 (choir)",
+    "Pad 5 (bowed)",
+    "Pad 6 (metallic)",
+    "Pad 7 (halo)",
+    "Pad 8 (sweep)",
+    "FX 1 (rain)",
+    "FX 2 (soundtrack)",
+    "FX 3 (crystal)",
+    "FX 4 (atmosphere)",
+    "FX 5 (brightness)",
+    "FX 6 (goblins)",
+    "FX 7 (echoes)",
+    "FX 8 (sci-fi)",
+    "Sitar",
+    "Banjo",
+    "Shamisen",
+    "Koto",
+    "Kalimba",
+    "Bag pipe",
+    "Fiddle",
+    "Shanai",
+    "Tinkle Bell",
+    "Agogo",
+    "Steel Drums",
+    "Woodblock",
+    "Taiko Drum",
+    "Melodic Tom",
+    "Synth Drum",
+    "Reverse Cymbal",
+    "Guitar Fret Noise",
+    "Breath Noise",
+    "Seashore",
+    "Bird Tweet",
+    "Telephone Ring",
+    "Helicopter",
+    "Applause",
+    "Gunshot",
+];
+
+/// General MIDI Level 2 variation tones as `(program, bank LSB, name)`, sorted by program and
+/// bank. Bank LSB 0 of every program is the Level 1 capital tone
+const GM2_VARIATIONS: &[(u8, u8, &str)] = &[
+    (0, 1, "Wide Acoustic Grand"),
+    (0, 2, "Dark Acoustic Grand"),
+    (1, 1, "Wide Bright Acoustic"),
+    (2, 1, "Wide Electric Grand"),
+    (3, 1, "Wide Honky-tonk"),
+    (4, 1, "Detuned Electric Piano 1"),
+    (4, 2, "Electric Piano 1 Variation"),
+    (4, 3, "60's Electric Piano"),
+    (5, 1, "Detuned Electric Piano 2"),
+    (5, 2, "Electric Piano 2 Variation"),
+    (5, 3, "Electric Piano Legend"),
+    (5, 4, "Electric Piano Phase"),
+    (6, 1, "Coupled Harpsichord"),
+    (6, 2, "Wide Harpsichord"),
+    (6, 3, "Open Harpsichord"),
+    (7, 1, "Pulse Clavinet"),
+    (11, 1, "Wet Vibraphone"),
+    (12, 1, "Wide Marimba"),
+    (14, 1, "Church Bell"),
+    (14, 2, "Carillon"),
+    (16, 1, "Detuned Drawbar Organ"),
+    (16, 2, "Italian 60's Organ"),
+    (16, 3, "Drawbar Organ 2"),
+    (17, 1, "Detuned Percussive Organ"),
+    (17, 2, "Percussive Organ 2"),
+    (18, 1, "Rotary Organ"),
+    (19, 1, "Church Organ (octave mix)"),
+    (19, 2, "Detuned Church Organ"),
+    (20, 1, "Puff Organ"),
+    (21, 1, "Accordion 2"),
+    (24, 1, "Ukulele"),
+    (24, 2, "Acoustic Guitar (nylon + key off)"),
+    (24, 3, "Acoustic Guitar (nylon 2)"),
+    (25, 1, "12-Strings Guitar"),
+    (25, 2, "Mandolin"),
+    (25, 3, "Steel Guitar with Body Sound"),
+    (26, 1, "Pedal Steel Guitar"),
+    (27, 1, "Detuned Clean Electric Guitar"),
+    (27, 2, "Mid Tone Guitar"),
+    (28, 1, "Funk Cutting Guitar"),
+    (28, 2, "Muted Velo-Sensitive Guitar"),
+    (28, 3, "Jazz Man"),
+    (29, 1, "Guitar Pinch"),
+    (30, 1, "Distortion Guitar (with Feedback)"),
+    (30, 2, "Distorted Rhythm Guitar"),
+    (31, 1, "Guitar Feedback"),
+    (33, 1, "Finger Slap Bass"),
+    (38, 1, "Synth Bass (warm)"),
+    (38, 2, "Synth Bass 3 (resonance)"),
+    (38, 3, "Clavi Bass"),
+    (38, 4, "Hammer"),
+    (39, 1, "Synth Bass 4 (attack)"),
+    (39, 2, "Synth Bass (rubber)"),
+    (39, 3, "Attack Pulse"),
+    (40, 1, "Slow Violin"),
+    (46, 1, "Yang Qin"),
+    (48, 1, "Orchestra Strings"),
+    (48, 2, "60's Strings"),
+    (50, 1, "Synth Strings 3"),
+    (52, 1, "Choir Aahs 2"),
+    (53, 1, "Humming"),
+    (54, 1, "Analog Voice"),
+    (55, 1, "Bass Hit"),
+    (55, 2, "6th Hit"),
+    (55, 3, "Euro Hit"),
+    (56, 1, "Dark Trumpet"),
+    (57, 1, "Trombone 2"),
+    (57, 2, "Bright Trombone"),
+    (59, 1, "Muted Trumpet 2"),
+    (60, 1, "French Horns 2 (warm)"),
+    (61, 1, "Brass Section 2 (octave mix)"),
+    (62, 1, "Synth Brass 3"),
+    (62, 2, "Analog Synth Brass 1"),
+    (62, 3, "Jump Brass"),
+    (63, 1, "Synth Brass 4"),
+    (63, 2, "Analog Synth Brass 2"),
+    (80, 1, "Square Lead 2"),
+    (80, 2, "Sine Lead"),
+    (81, 1, "Saw Lead 2"),
+    (81, 2, "Doctor Solo"),
+    (81, 3, "Natural Lead"),
+    (81, 4, "Sequenced Saw"),
+    (89, 1, "Sine Pad"),
+    (91, 1, "Itopia"),
+    (98, 1, "Synth Mallet"),
+    (104, 1, "Sitar 2"),
+    (107, 1, "Taisho Koto"),
+    (115, 1, "Castanets"),
+    (116, 1, "Concert Bass Drum"),
+    (117, 1, "Melodic Tom 2"),
+    (118, 1, "Rhythm Box Tom"),
+    (118, 2, "Electric Drum"),
+    (120, 1, "Guitar Cutting Noise"),
+    (120, 2, "Acoustic Bass String Slap"),
+    (121, 1, "Flute Key Click"),
+    (122, 1, "Rain"),
+    (122, 2, "Thunder"),
+    (122, 3, "Wind"),
+    (122, 4, "Stream"),
+    (122, 5, "Bubble"),
+    (123, 1, "Dog"),
+    (123, 2, "Horse Gallop"),
+    (123, 3, "Bird Tweet 2"),
+    (124, 1, "Telephone Ring 2"),
+    (124, 2, "Door Creaking"),
+    (124, 3, "Door"),
+    (124, 4, "Scratch"),
+    (124, 5, "Wind Chime"),
+    (125, 1, "Car Engine"),
+    (125, 2, "Car Stop"),
+    (125, 3, "Car Pass"),
+    (125, 4, "Car Crash"),
+    (125, 5, "Siren"),
+    (125, 6, "Train"),
+    (125, 7, "Jetplane"),
+    (125, 8, "Starship"),
+    (125, 9, "Burst Noise"),
+    (126, 1, "Laughing"),
+    (126, 2, "Screaming"),
+    (126, 3, "Punch"),
+    (126, 4, "Heart Beat"),
+    (126, 5, "Footsteps"),
+    (127, 1, "Machine Gun"),
+    (127, 2, "Lasergun"),
+    (127, 3, "Explosion"),
+];
+
+/// Roland GS variation tones as `(program, bank MSB, name)`, sorted by program and bank. Bank
+/// MSB 0 of every program is the capital tone
+const GS_VARIATIONS: &[(u8, u8, &str)] = &[
+    (0, 8, "Piano 1w"),
+    (1, 8, "Piano 2w"),
+    (2, 8, "Piano 3w"),
+    (3, 8, "Honky-tonk w"),
+    (4, 8, "Detuned EP 1"),
+    (5, 8, "Detuned EP 2"),
+    (6, 8, "Coupled Hps."),
+    (11, 8, "Vib.w"),
+    (12, 8, "Marimba w"),
+    (14, 8, "Church Bell"),
+    (16, 8, "Detuned Or.1"),
+    (17, 8, "Detuned Or.2"),
+    (19, 8, "Church Org.2"),
+    (19, 16, "Church Org.3"),
+    (21, 8, "Accordion It"),
+    (24, 8, "Ukulele"),
+    (25, 8, "12-str.Gt"),
+    (25, 16, "Mandolin"),
+    (26, 8, "Hawaiian Gt."),
+    (27, 8, "Chorus Gt."),
+    (28, 8, "Funk Gt."),
+    (30, 8, "Feedback Gt."),
+    (31, 8, "Gt. Feedback"),
+    (38, 8, "Synth Bass 3"),
+    (39, 8, "Synth Bass 4"),
+    (48, 8, "Orchestra"),
+    (50, 8, "Syn.Strings3"),
+    (61, 8, "Brass 2"),
+    (62, 8, "Synth Brass3"),
+    (63, 8, "Synth Brass4"),
+    (80, 8, "Sine Wave"),
+    (81, 8, "Doctor Solo"),
+    (107, 8, "Taisho Koto"),
+    (115, 8, "Castanets"),
+    (116, 8, "Concert BD"),
+    (117, 8, "Melo. Tom 2"),
+    (118, 8, "808 Tom"),
+    (120, 1, "Gt.Cut Noise"),
+    (120, 2, "String Slap"),
+    (121, 1, "Fl.Key Click"),
+    (122, 1, "Rain"),
+    (122, 2, "Thunder"),
+    (122, 3, "Wind"),
+    (122, 4, "Stream"),
+    (122, 5, "Bubble"),
+    (123, 1, "Dog"),
+    (123, 2, "Horse-Gallop"),
+    (123, 3, "Bird 2"),
+    (124, 1, "Telephone 2"),
+    (124, 2, "DoorCreaking"),
+    (124, 3, "Door"),
+    (124, 4, "Scratch"),
+    (124, 5, "Wind Chimes"),
+    (125, 1, "Car-Engine"),
+    (125, 2, "Car-Stop"),
+    (125, 3, "Car-Pass"),
+    (125, 4, "Car-Crash"),
+    (125, 5, "Siren"),
+    (125, 6, "Train"),
+    (125, 7, "Jetplane"),
+    (125, 8, "Starship"),
+    (125, 9, "Burst Noise"),
+    (126, 1, "Laughing"),
+    (126, 2, "Screaming"),
+    (126, 3, "Punch"),
+    (126, 4, "Heart Beat"),
+    (126, 5, "Footsteps"),
+    (127, 1, "Machine Gun"),
+    (127, 2, "Lasergun"),
+    (127, 3, "Explosion"),
+];
+
+/// Yamaha XG variation tones as `(program, bank LSB, name)` for bank MSB 0, sorted by program and
+/// bank. Bank LSB 0 of every program is the capital tone
+const XG_VARIATIONS: &[(u8, u8, &str)] = &[
+    (0, 1, "GrndPnoK"),
+    (0, 18, "MelloGrP"),
+    (0, 40, "PianoStr"),
+    (0, 41, "Dream"),
+    (1, 1, "BritePnK"),
+    (2, 1, "ElGrPnoK"),
+    (2, 32, "Det.CP80"),
+    (3, 1, "HonkyTnK"),
+    (4, 1, "El.Pno1K"),
+    (4, 18, "MelloEP1"),
+    (4, 32, "Chor.EP1"),
+    (4, 40, "HardEl.P"),
+    (4, 45, "VX El.P1"),
+    (4, 64, "60sEl.P"),
+    (5, 1, "El.Pno2K"),
+    (5, 32, "Chor.EP2"),
+    (5, 33, "DX Hard"),
+    (5, 34, "DXLegend"),
+    (5, 40, "DX Phase"),
+    (5, 41, "DX+Analg"),
+    (5, 42, "DXKotoEP"),
+    (5, 45, "VX El.P2"),
+    (6, 1, "Harpsi.K"),
+    (6, 25, "Harpsi.2"),
+    (6, 35, "Harpsi.3"),
+    (7, 1, "Clavi. K"),
+    (7, 27, "ClaviWah"),
+    (7, 64, "PulseClv"),
+    (7, 65, "PierceCl"),
+    (24, 16, "Nylon2"),
+    (24, 25, "Nylon3"),
+    (24, 43, "VelGtHrm"),
+    (24, 96, "Ukulele"),
+    (25, 16, "SteelGt2"),
+    (25, 35, "12StrGtr"),
+    (25, 40, "Nylon&St"),
+    (25, 41, "Stl&Body"),
+    (25, 96, "Mandolin"),
+];
+
+/// Drum kits shared by General MIDI Level 2, GS and XG, as `(program, name)`
+const DRUM_KITS: &[(u8, &str)] = &[
+    (0, "Standard Kit"),
+    (8, "Room Kit"),
+    (16, "Power Kit"),
+    (24, "Electronic Kit"),
+    (25, "Analog Kit"),
+    (32, "Jazz Kit"),
+    (40, "Brush Kit"),
+    (48, "Orchestra Kit"),
+    (56, "SFX Kit"),
+];
+
+/// Lowest key with a General MIDI Level 1 percussion sound
+const GM1_FIRST_PERCUSSION: u8 = 35;
+/// Lowest key with a General MIDI Level 2 percussion sound
+const GM2_FIRST_PERCUSSION: u8 = 27;
+
+/// General MIDI percussion names for keys 27 to 87. Level 1 only defines keys 35 to 81
+const PERCUSSION: [&str; 61] = [
+    "High Q",
+    "Slap",
+    "Scratch Push",
+    "Scratch Pull",
+    "Sticks",
+    "Square Click",
+    "Metronome Click",
+    "Metronome Bell",
+    "Acoustic Bass Drum",
+    "Bass Drum 1",
+    "Side Stick",
+    "Acoustic Snare",
+    "Hand Clap",
+    "Electric Snare",
+    "Low Floor Tom",
+    "Closed Hi Hat",
+    "High Floor Tom",
+    "Pedal Hi-Hat",
+    "Low Tom",
+    "Open Hi-Hat",
+    "Low-Mid Tom",
+    "Hi-Mid Tom",
+    "Crash Cymbal 1",
+    "High Tom",
+    "Ride Cymbal 1",
+    "Chinese Cymbal",
+    "Ride Bell",
+    "Tambourine",
+    "Splash Cymbal",
+    "Cowbell",
+    "Crash Cymbal 2",
+    "Vibraslap",
+    "Ride Cymbal 2",
+    "Hi Bongo",
+    "Low Bongo",
+    "Mute Hi Conga",
+    "Open Hi Conga",
+    "Low Conga",
+    "High Timbale",
+    "Low Timbale",
+    "High Agogo",
+    "Low Agogo",
+    "Cabasa",
+    "Maracas",
+    "Short Whistle",
+    "Long Whistle",
+    "Short Guiro",
+    "Long Guiro",
+    "Claves",
+    "Hi Wood Block",
+    "Low Wood Block",
+    "Mute Cuica",
+    "Open Cuica",
+    "Mute Triangle",
+    "Open Triangle",
+    "Shaker",
+    "Jingle Bell",
+    "Belltree",
+    "Castanets",
+    "Mute Surdo",
+    "Open Surdo",
+];
+
+/// Gets the General MIDI Level 1 name of a program
+pub fn gm_instrument_name(program: U7) -> &'static str {
+    GM_INSTRUMENTS[program.get() as usize]
+}
+
+/// Which instrument set a file was written for. This decides how bank select is read, which
+/// channels play percussion, and which names are known.
+///
+/// Bank variations without a known name fall back to the capital tone of their program, which is
+/// what General MIDI Level 2, GS and XG instruments do with banks they don't have
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SoundSet {
+    /// General MIDI Level 1, where bank select is ignored and channel 10 plays percussion
+    #[default]
+    Gm1,
+    /// General MIDI Level 2, where bank MSB 121 selects melodic tones, with the LSB selecting a
+    /// variation, and bank MSB 120 selects drum kits
+    Gm2,
+    /// Roland GS, where the bank MSB selects a variation and channel 10 plays percussion
+    Gs,
+    /// Yamaha XG, where the bank LSB selects a variation and bank MSBs 126 and 127 select drum
+    /// kits
+    Xg,
+}
+
+impl SoundSet {
+    /// Whether a channel with the given bank MSB, if one was sent, plays percussion
+    fn is_percussion(self, channel: Channel, bank_msb: Option<U7>) -> bool {
+        match (self, bank_msb.map(U7::get)) {
+            (Self::Gm2, Some(120)) | (Self::Xg, Some(126 | 127)) => true,
+            (Self::Gm2, Some(121)) | (Self::Xg, Some(_)) => false,
+            _ => channel == PERCUSSION_CHANNEL,
+        }
+    }
+
+    /// Gets the name of a melodic instrument
+    pub fn instrument_name(self, bank: U14, program: U7) -> &'static str {
+        let (variations, variation) = match self {
+            Self::Gm2 if bank.msb().get() == 121 => (GM2_VARIATIONS, bank.lsb().get()),
+            Self::Gs => (GS_VARIATIONS, bank.msb().get()),
+            Self::Xg if bank.msb().get() == 0 => (XG_VARIATIONS, bank.lsb().get()),
+            _ => (GM2_VARIATIONS, 0),
+        };
+
+        variations
+            .iter()
+            .find(|(p, v, _)| *p == program.get() && *v == variation)
+            .map_or_else(|| gm_instrument_name(program), |(_, _, name)| name)
+    }
+
+    /// Gets the name of a drum kit selected by program number
+    pub fn drum_kit_name(self, program: U7) -> &'static str {
+        let program = match self {
+            Self::Gm1 => 0,
+            _ => program.get(),
+        };
+
+        DRUM_KITS
+            .iter()
+            .find(|(p, _)| *p == program)
+            .map_or(DRUM_KITS[0].1, |(_, name)| name)
+    }
+
+    /// Gets the name of the percussion sound played by a key on a percussion channel, if it has
+    /// one
+    pub fn percussion_name(self, key: U7) -> Option<&'static str> {
+        let first = match self {
+            Self::Gm1 => GM1_FIRST_PERCUSSION,
+            _ => GM2_FIRST_PERCUSSION,
+        };
+        let last = match self {
+            Self::Gm1 => 81,
+            _ => GM2_FIRST_PERCUSSION + PERCUSSION.len() as u8 - 1,
+        };
+
+        (first..=last)
+            .contains(&key.get())
+            .then(|| PERCUSSION[(key.get() - GM2_FIRST_PERCUSSION) as usize])
+    }
+}
+
+/// An instrument selected on a channel by bank select and program change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instrument {
+    /// The instrument set the selection is read with
+    pub sound_set: SoundSet,
+    /// Bank selected by controllers 0 and 32 when the program changed
+    pub bank: U14,
+    /// The program number
+    pub program: U7,
+    /// Whether the instrument is a drum kit rather than a melodic instrument
+    pub percussion: bool,
+}
+
+impl Instrument {
+    /// Gets the instrument a channel plays before any program change
+    pub fn default_for(channel: Channel, sound_set: SoundSet) -> Self {
+        Self {
+            sound_set,
+            bank: U14::default(),
+            program: U7::MIN,
+            percussion: sound_set.is_percussion(channel, None),
+        }
+    }
+
+    /// Gets the instrument's name, or its drum kit's name if it's a percussion instrument
+    pub fn name(&self) -> &'static str {
+        if self.percussion {
+            self.sound_set.drum_kit_name(self.program)
+        } else {
+            self.sound_set.instrument_name(self.bank, self.program)
+        }
+    }
+
+    /// Gets the name of the sound a key plays on this instrument. Melodic instruments play their
+    /// own sound on every key
+    pub fn key_name(&self, key: U7) -> Option<&'static str> {
+        if self.percussion {
+            self.sound_set.percussion_name(key)
+        } else {
+            Some(self.name())
+        }
+    }
+}
+
+/// A channel switching instrument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrumentChange {
+    /// Absolute tick the instrument takes effect at
+    pub tick: u64,
+    /// The instrument from this tick onwards
+    pub instrument: Instrument,
+}
+
+/// The instrument each channel of a MIDI file plays over time.
+///
+/// Bank select only takes effect at the next program change on the same channel, as the MIDI
+/// 1.0 specification requires. Events from every track are gathered in time order
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentMap {
+    /// Instrument changes of each channel sorted by tick. Always starts with the default
+    /// instrument at tick 0
+    channels: [Vec<InstrumentChange>; 16],
+}
+
+impl InstrumentMap {
+    /// Builds the instrument map of a MIDI file, reading bank and program selections with a sound
+    /// set
+    pub fn new(midi: &Midi, sound_set: SoundSet) -> Self {
+        let mut channels: [Vec<InstrumentChange>; 16] = Default::default();
+        let mut banks = [(None, U7::MIN); 16];
+
+        for (index, changes) in channels.iter_mut().enumerate() {
+            changes.push(InstrumentChange {
+                tick: 0,
+                instrument: Instrument::default_for(Channel::saturating(index as u8), sound_set),
+            });
+        }
+
+        for merged in midi.merged_events() {
+            let Event::MidiEvent(event) = merged.event else {
+                continue;
+            };
+            let channel = event.channel();
+            let (msb, lsb) = &mut banks[channel.get() as usize];
+
+            match event {
+                MidiEvent::ControlChange(_, change) => match change.controller() {
+                    Controller::BankSelect => *msb = Some(change.new_value()),
+                    Controller::BankSelectLsb => *lsb = change.new_value(),
+                    _ => {}
+                },
+                MidiEvent::ProgramChange(_, program) => {
+                    let change = InstrumentChange {
+                        tick: merged.tick,
+                        instrument: Instrument {
+                            sound_set,
+                            bank: U14::from_lsb_msb(*lsb, msb.unwrap_or(U7::MIN)),
+                            program: *program,
+                            percussion: sound_set.is_percussion(channel, *msb),
+                        },
+                    };
+
+                    let changes = &mut channels[channel.get() as usize];
+                    match changes.last_mut() {
+                        Some(last) if last.tick == change.tick => *last = change,
+                        _ => changes.push(change),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self { channels }
+    }
+
+    /// Gets every instrument change on a channel in order, starting with the instrument in effect
+    /// at tick 0
+    pub fn changes(&self, channel: Channel) -> &[InstrumentChange] {
+        &self.channels[channel.get() as usize]
+    }
+
+    /// Gets the instrument a channel plays at a tick
+    pub fn instrument_at(&self, channel: Channel, tick: u64) -> Instrument {
+        let changes = self.changes(channel);
+        let index = changes
+            .partition_point(|change| change.tick <= tick)
+            .saturating_sub(1);
+        changes[index].instrument
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk::track::{
+            event::MidiEvent,
+            value::{Channel, U14, U7},
+            TrackChunk,
+        },
+        Midi,
+    };
+
+    use super::{
+        gm_instrument_name, InstrumentMap, SoundSet, GM2_VARIATIONS, GS_VARIATIONS, PERCUSSION,
+        XG_VARIATIONS,
+    };
+
+    #[test]
+    fn tables_name_programs_and_keys() {
+        assert_eq!(
+            gm_instrument_name(U7::new(24).unwrap()),
+            "Acoustic Guitar (nylon)"
+        );
+        assert_eq!(PERCUSSION.len(), 87 - 27 + 1);
+        for variations in [GM2_VARIATIONS, GS_VARIATIONS, XG_VARIATIONS] {
+            assert!(variations
+                .windows(2)
+                .all(|pair| (pair[0].0, pair[0].1) < (pair[1].0, pair[1].1)));
+            assert!(variations.iter().all(|(_, variation, _)| *variation != 0));
+        }
+
+        let key = |key| U7::new(key).unwrap();
+        assert_eq!(
+            SoundSet::Gm1.percussion_name(key(35)),
+            Some("Acoustic Bass Drum")
+        );
+        assert_eq!(
+            SoundSet::Gm1.percussion_name(key(81)),
+            Some("Open Triangle")
+        );
+        assert_eq!(SoundSet::Gm1.percussion_name(key(27)), None);
+        assert_eq!(SoundSet::Gm2.percussion_name(key(27)), Some("High Q"));
+        assert_eq!(SoundSet::Gm2.percussion_name(key(87)), Some("Open Surdo"));
+        assert_eq!(SoundSet::Gm2.percussion_name(key(88)), None);
+
+        let bank = U14::from_lsb_msb(key(1), key(121));
+        assert_eq!(SoundSet::Gm2.instrument_name(bank, key(24)), "Ukulele");
+        assert_eq!(
+            SoundSet::Gm1.instrument_name(bank, key(24)),
+            "Acoustic Guitar (nylon)"
+        );
+        assert_eq!(SoundSet::Gm2.instrument_name(bank, key(22)), "Harmonica");
+    }
+
+    #[test]
+    fn gs_and_xg_banks_name_their_variations() {
+        let key = |key| U7::new(key).unwrap();
+        let bank = |lsb, msb| U14::from_lsb_msb(key(lsb), key(msb));
+
+        assert_eq!(SoundSet::Gs.instrument_name(bank(0, 8), key(24)), "Ukulele");
+        assert_eq!(
+            SoundSet::Gs.instrument_name(bank(0, 16), key(25)),
+            "Mandolin"
+        );
+        assert_eq!(
+            SoundSet::Gs.instrument_name(bank(0, 1), key(127)),
+            "Machine Gun"
+        );
+        assert_eq!(
+            SoundSet::Gs.instrument_name(bank(8, 0), key(24)),
+            "Acoustic Guitar (nylon)"
+        );
+        assert_eq!(
+            SoundSet::Gs.instrument_name(bank(0, 8), key(22)),
+            "Harmonica"
+        );
+
+        assert_eq!(
+            SoundSet::Xg.instrument_name(bank(96, 0), key(24)),
+            "Ukulele"
+        );
+        assert_eq!(
+            SoundSet::Xg.instrument_name(bank(35, 0), key(25)),
+            "12StrGtr"
+        );
+        assert_eq!(
+            SoundSet::Xg.instrument_name(bank(96, 64), key(24)),
+            "Acoustic Guitar (nylon)"
+        );
+        assert_eq!(
+            SoundSet::Xg.instrument_name(bank(8, 0), key(24)),
+            "Acoustic Guitar (nylon)"
+        );
+    }
+
+    #[test]
+    fn instrument_map_tracks_bank_and_program_changes() {
+        let cc = |number, value| MidiEvent::control_change(0, number, value).unwrap();
+        let track = TrackChunk::builder()
+            .event(0, cc(0, 121))
+            .event(0, cc(32, 1))
+            .event(0, MidiEvent::program_change(0, 24).unwrap())
+            .event(96, cc(0, 120))
+            .event(0, MidiEvent::program_change(0, 8).unwrap())
+            .build()
+            .unwrap();
+        let midi = Midi::builder().track(track).build().unwrap();
+
+        let map = InstrumentMap::new(&midi, SoundSet::Gm2);
+        let channel = Channel::default();
+        assert_eq!(map.changes(channel).len(), 2);
+        assert_eq!(map.instrument_at(channel, 95).name(), "Ukulele");
+        assert_eq!(map.instrument_at(channel, 96).name(), "Room Kit");
+        assert!(map.instrument_at(channel, 96).percussion);
+
+        let drums = map.instrument_at(Channel::new(9).unwrap(), 0);
+        assert!(drums.percussion);
+        assert_eq!(drums.key_name(U7::new(38).unwrap()), Some("Acoustic Snare"));
+    }
+}
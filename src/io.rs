@@ -0,0 +1,48 @@
+//! Minimal I/O types used by the writer. With the `std` feature these are re-exports of
+//! [`std::io`]; without it a small [`Write`] trait, implemented for `Vec<u8>`, stands in so the
+//! writer works under `no_std`
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use no_std::{Error, Result, Write};
+
+/// Stand-ins for the [`std::io`] types used by the writer
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use alloc::vec::Vec;
+
+    /// Error returned by a [`Write`] implementor that couldn't accept more bytes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Error;
+
+    impl core::error::Error for Error {}
+    impl core::fmt::Display for Error {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write![f, "Failed to write MIDI bytes"]
+        }
+    }
+
+    /// Result of a write
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// A sink for bytes, the `no_std` counterpart of `std::io::Write`
+    pub trait Write {
+        /// Writes every byte of the buffer
+        fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+    }
+
+    impl Write for Vec<u8> {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+}
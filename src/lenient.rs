@@ -0,0 +1,521 @@
+//! Lenient parsing that recovers from malformed data where it can, reporting every recovery as
+//! a [`Diagnostic`]
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    chunk::{
+        chunk_types::{HEADER_CHUNK, TRACK_DATA_CHUNK},
+        header::{Format, HeaderChunk},
+        track::{meta::MetaEvent, Event, MTrkEvent, TrackChunk, TrackError, TrackParseState},
+        ParsedChunk,
+    },
+    reader::Counted,
+    Chunk, RawMidi,
+};
+
+/// How severe a recovery was
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The data was malformed but nothing was lost while recovering
+    Warning,
+    /// Data was dropped or guessed at while recovering
+    Error,
+}
+
+/// What was wrong and how it was recovered from
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    /// Bytes after the last chunk were too few to hold a chunk header and were ignored
+    TrailingBytes(usize),
+    /// A chunk's length runs past the end of the file, the available data was kept
+    TruncatedChunk {
+        /// Length the chunk declared
+        declared: usize,
+        /// Length of the data that was available
+        available: usize,
+    },
+    /// A header chunk couldn't be parsed and was dropped
+    InvalidHeader,
+    /// A header chunk declared an unknown format, format 1 was assumed
+    UnknownFormat(u16),
+    /// A header chunk was longer than 6 bytes, the extra bytes were ignored
+    OversizedHeader(usize),
+    /// The header's track count didn't match the number of track chunks, the count was
+    /// corrected
+    TrackCountMismatch {
+        /// Number of tracks the header declared
+        declared: u16,
+        /// Number of track chunks found
+        found: u16,
+    },
+    /// An event failed to parse and bytes were skipped until the next event
+    SkippedEvent {
+        /// Why the event failed to parse
+        error: TrackError,
+        /// Number of bytes skipped
+        skipped: usize,
+    },
+    /// The track ended partway through an event, which was dropped
+    TruncatedEvent(TrackError),
+    /// The track had no `EndOfTrack` event, one was added
+    MissingEndOfTrack,
+    /// Bytes after the track's `EndOfTrack` event were ignored
+    DataAfterEndOfTrack(usize),
+}
+
+/// A single recovery made while leniently parsing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// How severe the recovery was
+    pub severity: Severity,
+    /// Absolute byte offset in the input where the problem was found
+    pub offset: usize,
+    /// Index of the track chunk the problem was found in, if any
+    pub track: Option<usize>,
+    /// What was wrong
+    pub kind: DiagnosticKind,
+}
+
+impl core::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write![f, "{severity} at byte {}", self.offset]?;
+        if let Some(track) = self.track {
+            write![f, " in track {track}"]?;
+        }
+        write![f, ": "]?;
+
+        match &self.kind {
+            DiagnosticKind::TrailingBytes(count) => write![f, "ignored {count} trailing bytes"],
+            DiagnosticKind::TruncatedChunk {
+                declared,
+                available,
+            } => write![
+                f,
+                "chunk declared {declared} bytes but only {available} were available"
+            ],
+            DiagnosticKind::InvalidHeader => write![f, "dropped invalid header chunk"],
+            DiagnosticKind::UnknownFormat(format) => {
+                write![f, "unknown format {format}, assumed format 1"]
+            }
+            DiagnosticKind::OversizedHeader(len) => {
+                write![f, "ignored extra bytes in {len} byte header"]
+            }
+            DiagnosticKind::TrackCountMismatch { declared, found } => write![
+                f,
+                "header declared {declared} tracks but {found} were found"
+            ],
+            DiagnosticKind::SkippedEvent { error, skipped } => {
+                write![f, "skipped {skipped} bytes after invalid event: {error}"]
+            }
+            DiagnosticKind::TruncatedEvent(error) => {
+                write![f, "dropped truncated event: {error}"]
+            }
+            DiagnosticKind::MissingEndOfTrack => write![f, "added missing end of track"],
+            DiagnosticKind::DataAfterEndOfTrack(count) => {
+                write![f, "ignored {count} bytes after end of track"]
+            }
+        }
+    }
+}
+
+impl RawMidi {
+    /// Parses a stream of MIDI bytes, recovering from malformed data where possible instead of
+    /// failing. Every recovery is reported as a [`Diagnostic`]:
+    ///
+    /// - Invalid events are skipped up to the next delta time followed by an event that parses.
+    /// - Tracks without an `EndOfTrack` event are closed.
+    /// - A header track count that doesn't match the track chunks is corrected.
+    /// - A truncated final chunk is kept with the data that is available.
+    pub fn try_from_midi_stream_lenient<ITER>(stream: ITER) -> (RawMidi, Vec<Diagnostic>)
+    where
+        ITER: IntoIterator<Item = u8>,
+    {
+        let bytes: Vec<u8> = stream.into_iter().collect();
+        let mut parser = LenientParser::default();
+        let midi = parser.parse(&bytes);
+
+        (midi, parser.diagnostics)
+    }
+}
+
+/// State for a single lenient parse
+#[derive(Debug, Default)]
+struct LenientParser {
+    /// Recoveries made so far
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl LenientParser {
+    /// Records a diagnostic
+    fn report(
+        &mut self,
+        severity: Severity,
+        offset: usize,
+        track: Option<usize>,
+        kind: DiagnosticKind,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            offset,
+            track,
+            kind,
+        });
+    }
+
+    /// Parses every chunk in the input
+    fn parse(&mut self, bytes: &[u8]) -> RawMidi {
+        let mut chunks = vec![];
+        let mut header_at = None;
+        let mut tracks = 0;
+        let mut pos = 0;
+
+        while pos < bytes.len() {
+            let remaining = bytes.len() - pos;
+            if remaining < 8 {
+                self.report(
+                    Severity::Warning,
+                    pos,
+                    None,
+                    DiagnosticKind::TrailingBytes(remaining),
+                );
+                break;
+            }
+
+            // UNWRAP Safety: We verify at least 8 bytes remain before
+            let chunk: Chunk = u64::from_be_bytes(bytes[pos..pos + 8].try_into().unwrap()).into();
+            let start = pos + 8;
+            let end = start.saturating_add(chunk.len());
+
+            let data = if end > bytes.len() {
+                self.report(
+                    Severity::Error,
+                    pos,
+                    None,
+                    DiagnosticKind::TruncatedChunk {
+                        declared: chunk.len(),
+                        available: bytes.len() - start,
+                    },
+                );
+                &bytes[start..]
+            } else {
+                &bytes[start..end]
+            };
+
+            match chunk.chunk_type {
+                HEADER_CHUNK => {
+                    if let Some(header) = self.parse_header(data, pos) {
+                        header_at = header_at.or(Some((chunks.len(), pos)));
+                        chunks.push(ParsedChunk::Header(header));
+                    }
+                }
+                TRACK_DATA_CHUNK => {
+                    chunks.push(ParsedChunk::Track(self.parse_track(data, start, tracks)));
+                    tracks += 1;
+                }
+                chunk_type => chunks.push(ParsedChunk::Unknown {
+                    chunk_type,
+                    data: data.to_vec(),
+                }),
+            }
+
+            pos = start + data.len();
+        }
+
+        if let Some((index, offset)) = header_at {
+            if let ParsedChunk::Header(header) = &mut chunks[index] {
+                let found = tracks.min(u16::MAX as usize) as u16;
+                if header.ntrks() != found {
+                    self.report(
+                        Severity::Warning,
+                        offset,
+                        None,
+                        DiagnosticKind::TrackCountMismatch {
+                            declared: header.ntrks(),
+                            found,
+                        },
+                    );
+                    *header = HeaderChunk::from_parts(header.format(), found, header.division());
+                }
+            }
+        }
+
+        RawMidi { chunks }
+    }
+
+    /// Parses a header chunk's payload, falling back to format 1 for unknown formats
+    fn parse_header(&mut self, data: &[u8], offset: usize) -> Option<HeaderChunk> {
+        if data.len() < 6 {
+            self.report(Severity::Error, offset, None, DiagnosticKind::InvalidHeader);
+            return None;
+        }
+        if data.len() > 6 {
+            self.report(
+                Severity::Warning,
+                offset,
+                None,
+                DiagnosticKind::OversizedHeader(data.len()),
+            );
+        }
+
+        let format = u16::from_be_bytes([data[0], data[1]]);
+        let ntrks = u16::from_be_bytes([data[2], data[3]]);
+        let division = u16::from_be_bytes([data[4], data[5]]);
+
+        let format = Format::try_from(format).unwrap_or_else(|_| {
+            self.report(
+                Severity::Warning,
+                offset,
+                None,
+                DiagnosticKind::UnknownFormat(format),
+            );
+            Format::One
+        });
+
+        Some(HeaderChunk::from_parts(format, ntrks, division.into()))
+    }
+
+    /// Parses a track chunk's payload, skipping invalid events and closing the track if needed
+    fn parse_track(&mut self, data: &[u8], base: usize, track: usize) -> TrackChunk {
+        let mut mtrk_events = vec![];
+        let mut state = TrackParseState::default();
+        let mut pos = 0;
+        // Delta time of skipped events, carried over so later events keep their timing
+        let mut carried = 0u32;
+        let mut ended = false;
+
+        while pos < data.len() {
+            let mut bytes = Counted::new(data[pos..].iter().copied());
+            // There is always at least one byte left, so a delta time is always read
+            let delta_time = MTrkEvent::try_get_delta_time(&mut bytes).unwrap_or(0);
+            let status_pos = pos + bytes.count;
+
+            let mut bytes = Counted::new(data[status_pos..].iter().copied());
+            match Event::try_from_state(&mut bytes, &mut state) {
+                Ok(event) => {
+                    let is_end = matches!(event, Event::MetaEvent(MetaEvent::EndOfTrack));
+                    mtrk_events.push(MTrkEvent::from_parts(
+                        carried.saturating_add(delta_time),
+                        event,
+                    ));
+                    carried = 0;
+                    pos = status_pos + bytes.count;
+
+                    if is_end {
+                        ended = true;
+                        break;
+                    }
+                }
+                Err(error @ TrackError::OutOfSpace) => {
+                    self.report(
+                        Severity::Error,
+                        base + status_pos,
+                        Some(track),
+                        DiagnosticKind::TruncatedEvent(error),
+                    );
+                    carried = carried.saturating_add(delta_time);
+                    pos = data.len();
+                }
+                Err(error) => {
+                    let next = next_event_start(data, status_pos + 1);
+
+                    self.report(
+                        Severity::Error,
+                        base + status_pos,
+                        Some(track),
+                        DiagnosticKind::SkippedEvent {
+                            error,
+                            skipped: next - status_pos,
+                        },
+                    );
+
+                    carried = carried.saturating_add(delta_time);
+                    state = TrackParseState::default();
+                    pos = next;
+                }
+            }
+        }
+
+        if ended && pos < data.len() {
+            self.report(
+                Severity::Warning,
+                base + pos,
+                Some(track),
+                DiagnosticKind::DataAfterEndOfTrack(data.len() - pos),
+            );
+        }
+
+        if !ended {
+            self.report(
+                Severity::Warning,
+                base + data.len(),
+                Some(track),
+                DiagnosticKind::MissingEndOfTrack,
+            );
+            mtrk_events.push(MTrkEvent::from_parts(
+                carried,
+                Event::MetaEvent(MetaEvent::EndOfTrack),
+            ));
+        }
+
+        TrackChunk::new(mtrk_events)
+    }
+}
+
+/// Returns true if a byte can start an event in a track
+fn is_status_byte(byte: u8) -> bool {
+    matches!(byte, 0x80..=0xEF | 0xF0 | 0xF7 | 0xFF)
+}
+
+/// Finds the first position from `from` holding a delta time followed by a status byte whose
+/// event parses, or runs out of data, so parsing resumes on an event boundary. Returns the end
+/// of the data if there is none
+fn next_event_start(data: &[u8], from: usize) -> usize {
+    (from..data.len())
+        .find(|&start| {
+            // Delta times are at most 4 bytes, the last one without its high bit set
+            let Some(length) = data[start..]
+                .iter()
+                .take(4)
+                .position(|byte| byte & 0x80 == 0)
+            else {
+                return false;
+            };
+            let status_pos = start + length + 1;
+            if !data
+                .get(status_pos)
+                .is_some_and(|byte| is_status_byte(*byte))
+            {
+                return false;
+            }
+
+            let mut bytes = data[status_pos..].iter().copied();
+            matches!(
+                Event::try_from_state(&mut bytes, &mut TrackParseState::default()),
+                Ok(_) | Err(TrackError::OutOfSpace)
+            )
+        })
+        .unwrap_or(data.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk::{track::Event, ParsedChunk},
+        tests::{bytes_from_tracks, bytes_with_track_count},
+        writer::MidiWriteable,
+        RawMidi,
+    };
+
+    use super::{DiagnosticKind, Severity};
+
+    #[test]
+    fn well_formed_files_have_no_diagnostics() {
+        let bytes = bytes_from_tracks(96, &[&[0x00, 0x90, 0x3C, 0x40, 0x00, 0xFF, 0x2F, 0x00]]);
+        let (midi, diagnostics) = RawMidi::try_from_midi_stream_lenient(bytes.clone());
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(midi.to_midi_bytes(), bytes)
+    }
+
+    #[test]
+    fn unknown_status_bytes_are_skipped() {
+        let track: &[u8] = &[
+            0x00, 0x90, 0x3C, 0x40, // Note on at 0
+            0x10, 0xF4, // Undefined system common message at 16
+            0x10, 0x90, 0x3E, 0x40, // Note on at 32
+            0x81, 0x00, 0x80, 0x3C, 0x40, // Note off at 160
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let (midi, diagnostics) =
+            RawMidi::try_from_midi_stream_lenient(bytes_from_tracks(96, &[track]));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].offset, 14 + 8 + 5);
+        assert_eq!(diagnostics[0].track, Some(0));
+        assert!(matches!(
+            diagnostics[0].kind,
+            DiagnosticKind::SkippedEvent { skipped: 1, .. }
+        ));
+
+        let ParsedChunk::Track(track) = &midi.chunks[1] else {
+            panic!("Expected a track chunk");
+        };
+        let ticks: Vec<_> = track.absolute_events().map(|(tick, _)| tick).collect();
+        assert_eq!(ticks, vec![0, 32, 160, 160]);
+        assert_eq!(
+            track.clone().to_midi_bytes(),
+            vec![
+                0x00, 0x90, 0x3C, 0x40, //
+                0x20, 0x90, 0x3E, 0x40, //
+                0x81, 0x00, 0x80, 0x3C, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        )
+    }
+
+    #[test]
+    fn skipping_resumes_at_a_delta_time_with_status_bytes_in_it() {
+        let track: &[u8] = &[
+            0x00, 0xF4, 0x05, // Undefined system common message with a data byte
+            0x81, 0x40, 0x90, 0x3C, 0x40, // Note on 192 ticks later
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let (midi, diagnostics) =
+            RawMidi::try_from_midi_stream_lenient(bytes_from_tracks(96, &[track]));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            diagnostics[0].kind,
+            DiagnosticKind::SkippedEvent { skipped: 2, .. }
+        ));
+
+        let ParsedChunk::Track(track) = &midi.chunks[1] else {
+            panic!("Expected a track chunk");
+        };
+        let events: Vec<_> = track.absolute_events().collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, 192);
+        assert!(matches!(events[0].1, Event::MidiEvent(_)));
+    }
+
+    #[test]
+    fn truncated_files_are_closed_and_counted() {
+        let mut bytes =
+            bytes_with_track_count(3, 96, &[&[0x00, 0x90, 0x3C, 0x40, 0x10, 0x80, 0x3C]]);
+        // Declare more data than the file holds
+        bytes[14 + 7] = 0x20;
+
+        let (midi, diagnostics) = RawMidi::try_from_midi_stream_lenient(bytes);
+        let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind.clone()).collect();
+
+        assert!(matches!(
+            kinds[0],
+            DiagnosticKind::TruncatedChunk {
+                declared: 32,
+                available: 7
+            }
+        ));
+        assert!(matches!(kinds[1], DiagnosticKind::TruncatedEvent(_)));
+        assert_eq!(kinds[2], DiagnosticKind::MissingEndOfTrack);
+        assert_eq!(
+            kinds[3],
+            DiagnosticKind::TrackCountMismatch {
+                declared: 3,
+                found: 1
+            }
+        );
+
+        let midi = midi.check_into_midi().expect("Sanitize recovered MIDI");
+        assert_eq!(midi.header.ntrks(), 1);
+        assert_eq!(
+            midi.tracks[0].clone().to_midi_bytes(),
+            vec![0x00, 0x90, 0x3C, 0x40, 0x10, 0xFF, 0x2F, 0x00]
+        )
+    }
+}
@@ -12,13 +12,15 @@
 //! parse these sections of a MIDI file.
 //!
 //! - **Minimal dependencies**: Keeps your application lightweight and minimizes build complexity.
-//!     Opt in to serde support.
+//!   Opt in to serde support.
 //! - **Streaming-friendly**: Exposes traits and functions that can parse MIDI data from any
 //!   implementor of [`reader::MidiStream`], making it easier to handle data on the fly.
 //!
 //! ## Example Usage
 //!
 //! ```rust
+//! # #[cfg(feature = "std")]
+//! # {
 //! use miami::{reader::MidiReadable, Midi, RawMidi};
 //!
 //! // Load MIDI bytes (replace with your own source as needed).
@@ -35,6 +37,7 @@
 //! for chunk in midi.tracks.iter() {
 //!     println!("Track: {:?}", chunk);
 //! }
+//! # }
 //! ```
 //!
 //!
@@ -47,10 +50,31 @@
 //!   chunk types and lengths.
 //! - **[`reader`]**: Provides traits and types for streaming MIDI data. The [`MidiStream`]
 //!   trait and related helpers allow on-the-fly parsing from any data source.
+//! - **[`borrowed`]**: Parses MIDI data held in memory without copying its payloads.
+//! - **[`controller`]**: Names control change controllers, pairs 14 bit controller halves, and
+//!   decodes RPN and NRPN parameter changes.
+//! - **[`convert`]**: Converts files between formats 0, 1 and 2.
+//! - **[`instrument`]**: Names General MIDI instruments, drum kits and percussion keys, and
+//!   tracks the instrument each channel plays over time.
+//! - **[`lenient`]**: Recovers what it can from malformed files, reporting each recovery.
+//! - **[`merge`]**: Merges the events of every track into a single time-ordered stream.
+//! - **[`meter`]**: Provides the [`meter::MeterMap`] for converting ticks to bar, beat and tick
+//!   positions and iterating over measures.
+//! - **[`notes`]**: Pairs note on and note off events into notes with durations.
+//! - **[`pitch`]**: Names, parses and tunes the pitches of note keys.
+//! - **[`rmid`]**: Reads and writes MIDI files wrapped in RIFF `RMID` containers.
+//! - **[`tempo`]**: Provides the [`tempo::TempoMap`] for converting ticks to wall-clock time.
 //! - **`chunk_types`, `header`, and `track`**: Provide definitions for recognized MIDI
 //!   chunk types (e.g., `MThd` for the header and `MTrk` for track data) and the logic for
 //!   parsing their contents.
 //!
+//! ## `no_std` Support
+//!
+//! The `std` feature is enabled by default. Without it the crate builds under `no_std` with
+//! `alloc`: chunk, event, meta and sysex parsing and the writer remain available, while reading
+//! from paths and [`std::io::Read`] sources is left out. The writer then writes into the minimal
+//! [`io::Write`] trait, which is implemented for `Vec<u8>`.
+//!
 //! ## Extensibility
 //!
 //! While this crate focuses on parsing the structural aspects of MIDI files (chunks and headers),
@@ -59,15 +83,39 @@
 //! control of the MIDI event parsing layer.
 //!
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod borrowed;
 pub mod chunk;
+pub mod controller;
+pub mod convert;
+pub mod instrument;
+pub mod io;
+pub mod lenient;
+pub mod merge;
+pub mod meter;
+pub mod notes;
+pub mod pitch;
 pub mod reader;
+pub mod rmid;
+pub mod tempo;
 pub mod writer;
 
-use chunk::{header::HeaderChunk, track::TrackChunk, ChunkParseError, ParsedChunk};
+use alloc::{vec, vec::Vec};
+
+use chunk::{
+    chunk_types::{HEADER_CHUNK, TRACK_DATA_CHUNK},
+    header::{Division, Format, HeaderChunk},
+    track::TrackChunk,
+    ChunkParseError, ErrorLocation, OutOfRange, ParsedChunk, UnknownChunk,
+};
+use io::Write;
 use reader::MidiStream;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use writer::MidiWriteable;
+use writer::{MidiWriteable, WriteMidi, WriteOptions};
 
 /// An entire MIDI file as a raw sequence of parsed chunks
 #[derive(Debug, Clone, PartialEq)]
@@ -78,7 +126,8 @@ pub struct RawMidi {
 }
 
 impl RawMidi {
-    /// Constructs a new MIDI instance from a stream of MIDI bytes
+    /// Constructs a new MIDI instance from a stream of MIDI bytes. A stream that ends partway
+    /// through a chunk or fails to read returns [`ChunkParseError::Stream`]
     pub fn try_from_midi_stream<STREAM>(stream: STREAM) -> Result<Self, ChunkParseError>
     where
         STREAM: MidiStream,
@@ -86,24 +135,94 @@ impl RawMidi {
         Self::try_from(StreamWrapper(stream))
     }
 
+    /// Lazily walks the chunks of a MIDI file held in memory without decoding them, see
+    /// [`reader::LazyChunks`]
+    pub fn lazy_chunks(bytes: &[u8]) -> reader::LazyChunks<'_> {
+        reader::LazyChunks::new(bytes)
+    }
+
     /// Attempts to upgrade a `RawMidi` stream into a sanitized `Midi` struct. This means there
-    /// must be a single starting header and only track chunks afterwards
+    /// must be a single starting header and only track chunks afterwards. Chunks of unknown
+    /// types are kept in [`Midi::unknown_chunks`]
     pub fn check_into_midi(self) -> Result<Midi, MidiSanitizerError> {
         self.try_into()
     }
+
+    /// Attempts to upgrade a `RawMidi` stream into a sanitized `Midi` struct like
+    /// [`RawMidi::check_into_midi`], handling chunks of unknown types with the given policy
+    pub fn check_into_midi_with_policy(
+        self,
+        policy: UnknownChunkPolicy,
+    ) -> Result<Midi, MidiSanitizerError> {
+        let mut chunks = self.chunks.into_iter();
+        let first = chunks.next().ok_or(MidiSanitizerError::NoChunks)?;
+        let header = match first {
+            ParsedChunk::Header(header) => header,
+            _ => return Err(MidiSanitizerError::NoStartHeader),
+        };
+        let mut tracks = vec![];
+        let mut unknown_chunks = vec![];
+
+        for chunk in chunks {
+            match chunk {
+                ParsedChunk::Track(track) => tracks.push(track),
+                ParsedChunk::Header(_) => return Err(MidiSanitizerError::TooManyHeaders),
+                ParsedChunk::Unknown { chunk_type, data } => match policy {
+                    UnknownChunkPolicy::Keep => unknown_chunks.push(UnknownChunk {
+                        position: tracks.len(),
+                        chunk_type,
+                        data,
+                    }),
+                    UnknownChunkPolicy::Drop => {}
+                    UnknownChunkPolicy::Reject => return Err(MidiSanitizerError::UnknownChunk),
+                },
+            }
+        }
+
+        Ok(Midi {
+            header,
+            tracks,
+            unknown_chunks,
+        })
+    }
 }
 
 impl MidiWriteable for RawMidi {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let mut res = vec![];
-        for chunk in self.chunks {
-            res.extend(chunk.to_midi_bytes());
+        self.to_midi_vec(WriteOptions::default())
+    }
+
+    fn to_midi_bytes_with_options(self, options: WriteOptions) -> Vec<u8> {
+        self.to_midi_vec(options)
+    }
+}
+
+impl WriteMidi for RawMidi {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        for chunk in &self.chunks {
+            chunk.write_midi(writer, options)?;
         }
 
-        res
+        Ok(())
     }
 }
 
+/// How chunks of unknown types are handled when sanitizing a [`RawMidi`] into a [`Midi`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownChunkPolicy {
+    /// Keep unknown chunks in [`Midi::unknown_chunks`] so they are written back
+    #[default]
+    Keep,
+    /// Skip unknown chunks, as the MIDI specification asks readers to do
+    Drop,
+    /// Fail with [`MidiSanitizerError::UnknownChunk`]
+    Reject,
+}
+
 /// A MIDI File "cleaned" by enforcing a single header chunk and an arbitrary amount of Track
 /// chunks
 #[derive(Debug, Clone, PartialEq)]
@@ -113,18 +232,94 @@ pub struct Midi {
     pub header: HeaderChunk,
     /// All subsequent track chunks
     pub tracks: Vec<TrackChunk>,
+    /// Chunks of unknown types, written back among the tracks at their recorded position
+    pub unknown_chunks: Vec<UnknownChunk>,
+}
+
+impl Midi {
+    /// Creates a builder for a MIDI file
+    pub fn builder() -> MidiBuilder {
+        MidiBuilder::default()
+    }
+}
+
+/// Builder for a [`Midi`] file. The header's track count is set from the added tracks. Defaults
+/// to format 1 with 480 ticks per quarter note
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MidiBuilder {
+    /// Builder for the header chunk
+    header: chunk::header::HeaderChunkBuilder,
+    /// All added tracks
+    tracks: Vec<TrackChunk>,
+}
+
+impl MidiBuilder {
+    /// Sets the MIDI format
+    pub fn format(mut self, format: Format) -> Self {
+        self.header = self.header.format(format);
+        self
+    }
+
+    /// Sets the division
+    pub fn division(mut self, division: Division) -> Self {
+        self.header = self.header.division(division);
+        self
+    }
+
+    /// Adds a track
+    pub fn track(mut self, track: TrackChunk) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    /// Builds the MIDI file, failing if the header is invalid for the added tracks
+    pub fn build(self) -> Result<Midi, OutOfRange> {
+        let ntrks = u16::try_from(self.tracks.len()).map_err(|_| OutOfRange {
+            field: "track count",
+            value: self.tracks.len() as i64,
+        })?;
+        let header = self.header.ntrks(ntrks).build()?;
+
+        Ok(Midi {
+            header,
+            tracks: self.tracks,
+            unknown_chunks: vec![],
+        })
+    }
 }
 
 impl MidiWriteable for Midi {
     fn to_midi_bytes(self) -> Vec<u8> {
-        let mut res = vec![];
-        res.extend(ParsedChunk::Header(self.header).to_midi_bytes());
-        for track in self.tracks {
-            let wrapped = ParsedChunk::Track(track);
-            res.extend(wrapped.to_midi_bytes());
+        self.to_midi_vec(WriteOptions::default())
+    }
+
+    fn to_midi_bytes_with_options(self, options: WriteOptions) -> Vec<u8> {
+        self.to_midi_vec(options)
+    }
+}
+
+impl WriteMidi for Midi {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()> {
+        writer::write_chunk(writer, HEADER_CHUNK, &self.header, options)?;
+
+        let mut unknown_chunks = self.unknown_chunks.iter().peekable();
+        for (position, track) in self.tracks.iter().enumerate() {
+            while let Some(unknown) = unknown_chunks.next_if(|chunk| chunk.position <= position) {
+                unknown.write_midi(writer, options)?;
+            }
+
+            writer::write_chunk(writer, TRACK_DATA_CHUNK, track, options)?;
+        }
+
+        for unknown in unknown_chunks {
+            unknown.write_midi(writer, options)?;
         }
 
-        res
+        Ok(())
     }
 }
 
@@ -138,6 +333,8 @@ pub enum MidiSanitizerError {
     TooManyHeaders,
     /// No chunks at all
     NoChunks,
+    /// A chunk of an unknown type was found while using [`UnknownChunkPolicy::Reject`]
+    UnknownChunk,
 }
 impl core::error::Error for MidiSanitizerError {}
 impl core::fmt::Display for MidiSanitizerError {
@@ -146,6 +343,7 @@ impl core::fmt::Display for MidiSanitizerError {
             Self::NoStartHeader => write![f, "First ParsedChunk in sequence isn't a header"],
             Self::TooManyHeaders => write![f, "More than one header chunk identified"],
             Self::NoChunks => write![f, "No chunks present"],
+            Self::UnknownChunk => write![f, "Chunk of an unknown type found"],
         }
     }
 }
@@ -153,22 +351,7 @@ impl core::fmt::Display for MidiSanitizerError {
 impl TryFrom<RawMidi> for Midi {
     type Error = MidiSanitizerError;
     fn try_from(value: RawMidi) -> Result<Self, Self::Error> {
-        let mut chunks = value.chunks.into_iter();
-        let first = chunks.next().ok_or(MidiSanitizerError::NoChunks)?;
-        let header = match first {
-            ParsedChunk::Header(header) => header,
-            _ => return Err(MidiSanitizerError::NoStartHeader),
-        };
-        let mut tracks = vec![];
-
-        for track in chunks {
-            match track {
-                ParsedChunk::Track(track) => tracks.push(track),
-                _ => return Err(MidiSanitizerError::TooManyHeaders),
-            }
-        }
-
-        Ok(Self { header, tracks })
+        value.check_into_midi_with_policy(UnknownChunkPolicy::Keep)
     }
 }
 
@@ -184,9 +367,26 @@ where
     fn try_from(value: StreamWrapper<STREAM>) -> Result<Self, Self::Error> {
         let mut data = value.0;
         let mut chunks = vec![];
+        let mut offset = 0;
+
+        loop {
+            let location = ErrorLocation {
+                offset,
+                chunk: chunks.len(),
+                ..Default::default()
+            };
+            let Some((chunk, bytes)) = data
+                .read_chunk_data_pair()
+                .map_err(|source| ChunkParseError::Stream { location, source })?
+            else {
+                break;
+            };
 
-        while let Some(parsed) = data.read_chunk_data_pair().map(ParsedChunk::try_from) {
-            let parsed = parsed?;
+            let payload_offset = offset + 8;
+            offset = payload_offset + bytes.len();
+
+            let parsed = ParsedChunk::try_from((chunk, bytes))
+                .map_err(|e| e.in_chunk(chunks.len(), payload_offset))?;
             chunks.push(parsed);
         }
 
@@ -235,8 +435,74 @@ impl From<u64> for Chunk {
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::Chunk;
+pub(crate) mod tests {
+    use crate::{
+        chunk::{
+            header::{Division, Format},
+            track::{event::MidiEvent, TrackChunk, TrackError},
+            ErrorLocation,
+        },
+        writer::MidiWriteable,
+        Chunk, Midi, MidiSanitizerError, RawMidi, UnknownChunkPolicy,
+    };
+
+    /// Builds the bytes of a format 1 MIDI file from a division and raw track payloads
+    pub(crate) fn bytes_from_tracks(division: u16, tracks: &[&[u8]]) -> Vec<u8> {
+        bytes_with_track_count(tracks.len() as u16, division, tracks)
+    }
+
+    /// Builds the bytes of a format 1 MIDI file whose header declares `ntrks` tracks, which
+    /// needn't match the number of raw track payloads
+    pub(crate) fn bytes_with_track_count(ntrks: u16, division: u16, tracks: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = vec![b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 1];
+        bytes.extend(ntrks.to_be_bytes());
+        bytes.extend(division.to_be_bytes());
+
+        for track in tracks {
+            bytes.extend(b"MTrk");
+            bytes.extend((track.len() as u32).to_be_bytes());
+            bytes.extend(track.iter());
+        }
+
+        bytes
+    }
+
+    /// Builds a format 1 MIDI file from a division and raw track payloads
+    pub(crate) fn midi_from_tracks(division: u16, tracks: &[&[u8]]) -> Midi {
+        RawMidi::try_from_midi_stream(bytes_from_tracks(division, tracks).into_iter())
+            .expect("Parse MIDI stream")
+            .check_into_midi()
+            .expect("Sanitize MIDI")
+    }
+
+    #[test]
+    fn parse_errors_locate_the_offending_byte() {
+        let bytes = bytes_from_tracks(
+            96,
+            &[
+                &[0x00, 0xFF, 0x2F, 0x00],
+                &[0x00, 0x90, 0x3C, 0x40, 0x10, 0xF4, 0x00],
+            ],
+        );
+
+        let error = RawMidi::try_from_midi_stream(bytes.into_iter()).expect_err("Invalid status");
+        assert_eq!(
+            error.location(),
+            Some(&ErrorLocation {
+                offset: 14 + 12 + 8 + 5,
+                chunk: 2,
+                event: Some(1),
+                byte: Some(0xF4),
+            })
+        );
+
+        let source = core::error::Error::source(&error).expect("Track error source");
+        assert_eq!(source.to_string(), TrackError::InvalidFormat.to_string());
+        assert_eq!(
+            error.to_string(),
+            "Track parsing error at offset 39 in chunk 2, event 1 (byte 0xF4): Invalid Track Format"
+        )
+    }
 
     #[test]
     fn chunk_from_raw_u64_behaves_normally() {
@@ -248,4 +514,63 @@ mod tests {
 
         assert_eq!(expected, message.into())
     }
+
+    #[test]
+    fn unknown_chunks_follow_policy() {
+        let mut bytes = vec![b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 1, 0, 1, 0, 96];
+        bytes.extend(b"XFIH\0\0\0\x02\x01\x02");
+        bytes.extend(b"MTrk\0\0\0\x04\x00\xFF\x2F\x00");
+        bytes.extend(b"XFKM\0\0\0\x01\x03");
+
+        let raw = RawMidi::try_from_midi_stream(bytes.clone().into_iter())
+            .expect("Parse MIDI stream with unknown chunks");
+        assert_eq!(raw.chunks.len(), 4);
+        assert_eq!(raw.clone().to_midi_bytes(), bytes);
+
+        let kept = raw.clone().check_into_midi().expect("Sanitize MIDI");
+        assert_eq!(kept.unknown_chunks.len(), 2);
+        assert_eq!(kept.to_midi_bytes(), bytes);
+
+        let dropped = raw
+            .clone()
+            .check_into_midi_with_policy(UnknownChunkPolicy::Drop)
+            .expect("Sanitize MIDI");
+        assert!(dropped.unknown_chunks.is_empty());
+        assert_eq!(dropped.tracks.len(), 1);
+
+        assert_eq!(
+            raw.check_into_midi_with_policy(UnknownChunkPolicy::Reject),
+            Err(MidiSanitizerError::UnknownChunk)
+        )
+    }
+
+    #[test]
+    fn midi_builder_round_trips() {
+        let track = TrackChunk::builder()
+            .event(0, MidiEvent::note_on(9, 36, 127).unwrap())
+            .event(48, MidiEvent::note_off(9, 36, 0).unwrap())
+            .build()
+            .expect("Build track");
+        let midi = Midi::builder()
+            .format(Format::Zero)
+            .division(Division::Metrical(96))
+            .track(track)
+            .build()
+            .expect("Build MIDI");
+
+        assert_eq!(midi.header.ntrks(), 1);
+
+        let parsed = RawMidi::try_from_midi_stream(midi.clone().to_midi_bytes().into_iter())
+            .expect("Parse MIDI stream")
+            .check_into_midi()
+            .expect("Sanitize MIDI");
+        assert_eq!(parsed, midi);
+
+        let too_many = Midi::builder()
+            .format(Format::Zero)
+            .track(TrackChunk::default())
+            .track(TrackChunk::default())
+            .build();
+        assert!(too_many.is_err())
+    }
 }
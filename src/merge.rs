@@ -0,0 +1,154 @@
+//! Time-ordered merging of the events of every track in a MIDI file
+
+use alloc::{vec, vec::Vec};
+
+use core::iter::Peekable;
+
+use crate::{
+    chunk::track::{meta::MetaEvent, AbsoluteEvents, Event},
+    Midi,
+};
+
+/// An event from a merged stream, tagged with the track it came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergedEvent<'a> {
+    /// Absolute tick the event occurs at
+    pub tick: u64,
+    /// Index of the source track within [`Midi::tracks`]
+    pub track: usize,
+    /// The event itself
+    pub event: &'a Event,
+}
+
+/// Iterator over the events of every track in a MIDI file in time order, created by
+/// [`Midi::merged_events`].
+///
+/// Events at the same tick are ordered meta events first, then channel and system exclusive
+/// events, then `EndOfTrack`, with ties broken by track index. Events from the same track are
+/// never reordered, so an event only moves ahead of earlier events from other tracks
+#[derive(Debug, Clone)]
+pub struct MergedEvents<'a> {
+    /// Remaining events of every track
+    tracks: Vec<Peekable<AbsoluteEvents<'a>>>,
+    /// Events of every track at the current tick, each in reverse order so they can be popped
+    pending: Vec<Vec<MergedEvent<'a>>>,
+}
+
+/// Orders events at the same tick: meta events, then channel and system exclusive events, then
+/// `EndOfTrack`
+fn same_tick_rank(event: &Event) -> u8 {
+    match event {
+        Event::MetaEvent(MetaEvent::EndOfTrack) => 2,
+        Event::MetaEvent(_) => 0,
+        _ => 1,
+    }
+}
+
+impl<'a> Iterator for MergedEvents<'a> {
+    type Item = MergedEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.iter().all(Vec::is_empty) {
+            let tick = self
+                .tracks
+                .iter_mut()
+                .filter_map(|track| track.peek().map(|(tick, _)| *tick))
+                .min()?;
+
+            for (index, (track, pending)) in
+                self.tracks.iter_mut().zip(&mut self.pending).enumerate()
+            {
+                while let Some((_, event)) = track.next_if(|(next, _)| *next == tick) {
+                    pending.push(MergedEvent {
+                        tick,
+                        track: index,
+                        event,
+                    });
+                }
+                pending.reverse();
+            }
+        }
+
+        self.pending
+            .iter_mut()
+            .filter(|pending| !pending.is_empty())
+            .min_by_key(|pending| pending.last().map(|merged| same_tick_rank(merged.event)))?
+            .pop()
+    }
+}
+
+impl Midi {
+    /// Iterates over the events of every track in time order, each tagged with the index of its
+    /// source track. See [`MergedEvents`] for how events at the same tick are ordered
+    pub fn merged_events(&self) -> MergedEvents<'_> {
+        MergedEvents {
+            tracks: self
+                .tracks
+                .iter()
+                .map(|track| track.absolute_events().peekable())
+                .collect(),
+            pending: vec![vec![]; self.tracks.len()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk::track::{meta::MetaEvent, Event},
+        tests::midi_from_tracks,
+    };
+
+    #[test]
+    fn events_are_merged_in_time_order() {
+        let first: &[u8] = &[
+            0x00, 0x90, 0x3C, 0x40, // Note on at 0
+            0x20, 0x80, 0x3C, 0x40, // Note off at 32
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 32
+        ];
+        let second: &[u8] = &[
+            0x10, 0x91, 0x40, 0x40, // Note on at 16
+            0x20, 0x81, 0x40, 0x40, // Note off at 48
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 48
+        ];
+        let midi = midi_from_tracks(96, &[first, second]);
+
+        let order: Vec<_> = midi
+            .merged_events()
+            .map(|merged| (merged.tick, merged.track))
+            .collect();
+
+        assert_eq!(
+            order,
+            vec![(0, 0), (16, 1), (32, 0), (32, 0), (48, 1), (48, 1)]
+        )
+    }
+
+    #[test]
+    fn meta_events_come_first_at_the_same_tick() {
+        let notes: &[u8] = &[
+            0x00, 0x90, 0x3C, 0x40, // Note on at 0
+            0x00, 0xFF, 0x01, 0x01, b'a', // Text at 0, after the note on
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 0
+        ];
+        let conductor: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Tempo at 0
+            0x00, 0xFF, 0x2F, 0x00, // End of track at 0
+        ];
+        let midi = midi_from_tracks(96, &[notes, conductor]);
+
+        let merged: Vec<_> = midi
+            .merged_events()
+            .map(|merged| (merged.track, merged.event))
+            .collect();
+
+        assert_eq!(merged.len(), 5);
+        assert_eq!(merged[0], (1, &Event::MetaEvent(MetaEvent::Tempo(500_000))));
+        assert_eq!(merged[1].0, 0);
+        assert!(matches!(merged[1].1, Event::MidiEvent(_)));
+        assert_eq!(merged[2].0, 0);
+        assert!(matches!(merged[2].1, Event::MetaEvent(MetaEvent::Text(_))));
+        assert_eq!(merged[3], (0, &Event::MetaEvent(MetaEvent::EndOfTrack)));
+        assert_eq!(merged[4], (1, &Event::MetaEvent(MetaEvent::EndOfTrack)));
+    }
+}
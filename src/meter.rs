@@ -0,0 +1,404 @@
+//! Meter maps for converting between ticks and bar, beat and tick positions, and iterating over
+//! the measures of a file
+
+use alloc::vec::Vec;
+
+use core::iter::Peekable;
+
+use crate::{
+    chunk::{
+        header::Division,
+        track::{meta::MetaEvent, meta::TimeSignature, Event},
+    },
+    merge::{MergedEvent, MergedEvents},
+    Midi,
+};
+
+/// Time signature in effect before the first time signature event, 4/4 with a click every
+/// quarter note
+pub const DEFAULT_TIME_SIGNATURE: TimeSignature = TimeSignature::COMMON_TIME;
+
+/// A musical position, displayed as `bar.beat.tick`. Bars and beats count from 1, so the first
+/// tick of a file is `1.1.0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BbtPosition {
+    /// The bar, counting from 1
+    pub bar: u64,
+    /// The beat within the bar, counting from 1
+    pub beat: u64,
+    /// Ticks after the start of the beat
+    pub tick: u64,
+}
+
+impl core::fmt::Display for BbtPosition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![f, "{}.{}.{}", self.bar, self.beat, self.tick]
+    }
+}
+
+/// A single time signature change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterChange {
+    /// Absolute tick the time signature takes effect at
+    pub tick: u64,
+    /// Index of the bar starting at the change, counting from 0
+    pub bar: u64,
+    /// Time signature from this tick onwards
+    pub time_signature: TimeSignature,
+}
+
+/// Every time signature change in a metrical MIDI file, used to convert between ticks and bar,
+/// beat and tick positions.
+///
+/// Time signature changes from every track are gathered, and the latest track wins when several
+/// change at the same tick. A change that doesn't fall on a bar line cuts the bar it falls in
+/// short and starts a new bar. A beat is the note value of the time signature's denominator
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterMap {
+    /// Ticks per quarter note
+    ticks_per_quarter: u16,
+    /// Time signature changes sorted by tick. Always starts with a change at tick 0
+    changes: Vec<MeterChange>,
+    /// Absolute tick of the last event in the file
+    end_tick: u64,
+}
+
+impl MeterMap {
+    /// Builds the meter map of a MIDI file. Returns `None` for [`Division::TimeCodeBased`]
+    /// files, whose ticks don't follow the beat
+    pub fn new(midi: &Midi) -> Option<Self> {
+        let Division::Metrical(ticks_per_quarter) = midi.header.division() else {
+            return None;
+        };
+
+        let mut map = Self {
+            ticks_per_quarter,
+            changes: Vec::new(),
+            end_tick: 0,
+        };
+        map.changes.push(MeterChange {
+            tick: 0,
+            bar: 0,
+            time_signature: DEFAULT_TIME_SIGNATURE,
+        });
+
+        for merged in midi.merged_events() {
+            map.end_tick = merged.tick;
+            let Event::MetaEvent(MetaEvent::TimeSignature(time_signature)) = merged.event else {
+                continue;
+            };
+
+            if map
+                .changes
+                .last()
+                .is_some_and(|last| last.tick == merged.tick)
+            {
+                map.changes.pop();
+            }
+            let bar = match map.changes.last() {
+                Some(previous) => {
+                    previous.bar + (merged.tick - previous.tick).div_ceil(map.bar_ticks(previous))
+                }
+                None => 0,
+            };
+
+            map.changes.push(MeterChange {
+                tick: merged.tick,
+                bar,
+                time_signature: *time_signature,
+            });
+        }
+
+        Some(map)
+    }
+
+    /// Gets every time signature change in order, starting with the one in effect at tick 0
+    pub fn changes(&self) -> &[MeterChange] {
+        &self.changes
+    }
+
+    /// Gets the time signature in effect at a tick
+    pub fn time_signature_at(&self, tick: u64) -> TimeSignature {
+        self.change_for_tick(tick).time_signature
+    }
+
+    /// Gets the absolute tick of the last event in the file
+    pub fn end_tick(&self) -> u64 {
+        self.end_tick
+    }
+
+    /// Converts an absolute tick to the bar, beat and tick it falls on
+    pub fn tick_to_bbt(&self, tick: u64) -> BbtPosition {
+        let change = self.change_for_tick(tick);
+        let bar_ticks = self.bar_ticks(change);
+        let beat_ticks = self.beat_ticks(change);
+
+        let offset = tick - change.tick;
+        let within_bar = offset % bar_ticks;
+        // Rounding can leave a few ticks past the last full beat, which belong to the last beat
+        let beats = change.time_signature.numerator().max(1) as u64;
+        let beat = (within_bar / beat_ticks).min(beats - 1);
+
+        BbtPosition {
+            bar: change.bar + offset / bar_ticks + 1,
+            beat: beat + 1,
+            tick: within_bar - beat * beat_ticks,
+        }
+    }
+
+    /// Converts a bar, beat and tick position to the absolute tick it falls on. Beats and ticks
+    /// past the end of their bar carry on into the following bars
+    pub fn bbt_to_tick(&self, position: BbtPosition) -> u64 {
+        let bar = position.bar.saturating_sub(1);
+        let index = self
+            .changes
+            .partition_point(|change| change.bar <= bar)
+            .saturating_sub(1);
+        let change = &self.changes[index];
+
+        change.tick
+            + (bar - change.bar) * self.bar_ticks(change)
+            + position.beat.saturating_sub(1) * self.beat_ticks(change)
+            + position.tick
+    }
+
+    /// Iterates over the measures of a file, up to and including the one holding its last
+    /// event. The file should be the one the map was built from
+    pub fn measures<'a>(&'a self, midi: &'a Midi) -> Measures<'a> {
+        Measures {
+            map: self,
+            events: midi.merged_events().peekable(),
+            bar: 0,
+            tick: 0,
+        }
+    }
+
+    /// Finds the time signature change in effect at a tick
+    fn change_for_tick(&self, tick: u64) -> &MeterChange {
+        let index = self
+            .changes
+            .partition_point(|change| change.tick <= tick)
+            .saturating_sub(1);
+        &self.changes[index]
+    }
+
+    /// Gets the length of a full bar while a time signature is in effect, at least one tick. Bars
+    /// of parsed time signatures with no beats are treated as a single beat long
+    fn bar_ticks(&self, change: &MeterChange) -> u64 {
+        if change.time_signature.numerator() == 0 {
+            return self.beat_ticks(change);
+        }
+
+        let division = Division::Metrical(self.ticks_per_quarter);
+        change
+            .time_signature
+            .bar_ticks(division)
+            .unwrap_or(0)
+            .max(1)
+    }
+
+    /// Gets the length of a beat while a time signature is in effect, at least one tick
+    fn beat_ticks(&self, change: &MeterChange) -> u64 {
+        let division = Division::Metrical(self.ticks_per_quarter);
+        change
+            .time_signature
+            .beat_ticks(division)
+            .unwrap_or(0)
+            .max(1)
+    }
+}
+
+/// A single bar of a file, created by [`MeterMap::measures`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measure<'a> {
+    /// The bar's number, counting from 1
+    pub number: u64,
+    /// Absolute tick the bar starts at
+    pub start_tick: u64,
+    /// Absolute tick the next bar starts at
+    pub end_tick: u64,
+    /// Time signature of the bar
+    pub time_signature: TimeSignature,
+    /// Every event in the bar in time order, tagged with its source track
+    pub events: Vec<MergedEvent<'a>>,
+}
+
+/// Iterator over the measures of a file, created by [`MeterMap::measures`]
+#[derive(Debug, Clone)]
+pub struct Measures<'a> {
+    /// The file's meter map
+    map: &'a MeterMap,
+    /// Events not yet placed in a measure
+    events: Peekable<MergedEvents<'a>>,
+    /// Index of the next bar
+    bar: u64,
+    /// Absolute tick the next bar starts at
+    tick: u64,
+}
+
+impl<'a> Iterator for Measures<'a> {
+    type Item = Measure<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.tick > self.map.end_tick {
+            return None;
+        }
+
+        let change = self.map.change_for_tick(self.tick);
+        let mut end_tick = self.tick + self.map.bar_ticks(change);
+        if let Some(next) = self.map.changes.iter().find(|next| next.tick > self.tick) {
+            end_tick = end_tick.min(next.tick);
+        }
+
+        let mut events = Vec::new();
+        while let Some(event) = self.events.next_if(|event| event.tick < end_tick) {
+            events.push(event);
+        }
+
+        self.bar += 1;
+        let measure = Measure {
+            number: self.bar,
+            start_tick: self.tick,
+            end_tick,
+            time_signature: change.time_signature,
+            events,
+        };
+        self.tick = end_tick;
+
+        Some(measure)
+    }
+}
+
+impl core::iter::FusedIterator for Measures<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use crate::{
+        chunk::track::{
+            event::MidiEvent,
+            meta::{MetaEvent, TimeSignature},
+            TrackChunk,
+        },
+        tests::midi_from_tracks,
+        Midi,
+    };
+
+    use super::{BbtPosition, MeterMap};
+
+    /// Builds a file at 480 ticks per quarter with a bar of 4/4, a bar of 3/4 and then 6/8, and
+    /// a note in the middle of the 6/8 section
+    fn midi() -> Midi {
+        let time_signature = |numerator, denominator| {
+            MetaEvent::TimeSignature(TimeSignature::new(numerator, denominator, 24, 8).unwrap())
+        };
+        let track = TrackChunk::builder()
+            .event(0, time_signature(4, 4))
+            .event(1920, time_signature(3, 4))
+            .event(1440, time_signature(6, 8))
+            .event(1560, MidiEvent::note_on(0, 60, 100).unwrap())
+            .event(1440, MidiEvent::note_off(0, 60, 0).unwrap())
+            .build()
+            .unwrap();
+        Midi::builder().track(track).build().unwrap()
+    }
+
+    #[test]
+    fn ticks_convert_to_and_from_bbt_positions() {
+        let midi = midi();
+        let map = MeterMap::new(&midi).unwrap();
+        let bbt = |bar, beat, tick| BbtPosition { bar, beat, tick };
+
+        let positions = [
+            (0, bbt(1, 1, 0)),
+            (1919, bbt(1, 4, 479)),
+            (1920, bbt(2, 1, 0)),
+            (3360, bbt(3, 1, 0)),
+            (4920, bbt(4, 1, 120)),
+            (6120, bbt(4, 6, 120)),
+            (6360, bbt(5, 1, 120)),
+        ];
+        for (tick, position) in positions {
+            assert_eq!(map.tick_to_bbt(tick), position);
+            assert_eq!(map.bbt_to_tick(position), tick);
+        }
+        assert_eq!(map.tick_to_bbt(4920).to_string(), "4.1.120");
+        assert_eq!(map.time_signature_at(3359).numerator(), 3);
+    }
+
+    #[test]
+    fn mid_bar_changes_start_a_new_bar() {
+        let track = TrackChunk::builder()
+            .event(
+                960,
+                MetaEvent::TimeSignature(TimeSignature::new(3, 4, 24, 8).unwrap()),
+            )
+            .build()
+            .unwrap();
+        let midi = Midi::builder().track(track).build().unwrap();
+        let map = MeterMap::new(&midi).unwrap();
+
+        assert_eq!(map.changes()[1].bar, 1);
+        assert_eq!(map.tick_to_bbt(960).bar, 2);
+        assert_eq!(
+            map.measures(&midi)
+                .map(|measure| (measure.start_tick, measure.end_tick))
+                .collect::<Vec<_>>(),
+            vec![(0, 960), (960, 2400)]
+        );
+    }
+
+    #[test]
+    fn empty_time_signatures_count_as_one_beat() {
+        let track: &[u8] = &[
+            0x00, 0xFF, 0x58, 0x04, 0x00, 0x02, 0x18, 0x08, // 0/4 time signature
+            0x60, 0xFF, 0x2F, 0x00, // End of track at 96
+        ];
+        let midi = midi_from_tracks(96, &[track]);
+        let map = MeterMap::new(&midi).unwrap();
+
+        assert_eq!(map.time_signature_at(0).numerator(), 0);
+        assert_eq!(
+            map.tick_to_bbt(96),
+            BbtPosition {
+                bar: 2,
+                beat: 1,
+                tick: 0
+            }
+        );
+        assert_eq!(map.tick_to_bbt(120).to_string(), "2.1.24");
+        assert_eq!(map.measures(&midi).count(), 2);
+    }
+
+    #[test]
+    fn measures_hold_their_events() {
+        let midi = midi();
+        let map = MeterMap::new(&midi).unwrap();
+        let measures: Vec<_> = map.measures(&midi).collect();
+
+        let ranges: Vec<_> = measures
+            .iter()
+            .map(|measure| (measure.number, measure.start_tick, measure.end_tick))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (1, 0, 1920),
+                (2, 1920, 3360),
+                (3, 3360, 4800),
+                (4, 4800, 6240),
+                (5, 6240, 7680)
+            ]
+        );
+
+        assert_eq!(measures[2].time_signature.denominator().get(), 8);
+        assert_eq!(measures[3].events.len(), 1);
+        assert!(matches!(
+            measures[3].events[0].event,
+            crate::chunk::track::Event::MidiEvent(MidiEvent::NoteOn(..))
+        ));
+        let events: usize = measures.iter().map(|measure| measure.events.len()).sum();
+        assert_eq!(events, midi.merged_events().count());
+    }
+}
@@ -0,0 +1,270 @@
+//! Pairing of `NoteOn` and `NoteOff` events into notes with durations
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    vec::Vec,
+};
+
+use crate::{
+    chunk::{
+        track::{event::MidiEvent, meta::MetaEvent, Event, TrackChunk},
+        OutOfRange,
+    },
+    Midi,
+};
+
+/// A note, built from a matching pair of `NoteOn` and `NoteOff` events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note {
+    /// Channel the note is played on
+    pub channel: u8,
+    /// The note's key
+    pub key: u8,
+    /// Velocity the note was struck with
+    pub velocity: u8,
+    /// Absolute tick the note starts at
+    pub start_tick: u64,
+    /// Number of ticks the note is held for
+    pub duration_ticks: u64,
+    /// Velocity the note was released with, 0 when released by a `NoteOn` with velocity 0
+    pub release_velocity: u8,
+}
+
+impl Note {
+    /// Gets the absolute tick the note ends at
+    pub fn end_tick(&self) -> u64 {
+        self.start_tick + self.duration_ticks
+    }
+}
+
+/// How overlapping notes of the same pitch and channel are matched with note offs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteMatching {
+    /// A note off ends the earliest still sounding note
+    #[default]
+    Fifo,
+    /// A note off ends the latest still sounding note
+    Lifo,
+}
+
+/// Notes extracted from a track
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notes {
+    /// Every note that was ended by a note off, ordered by start tick
+    pub notes: Vec<Note>,
+    /// Notes that were still sounding when the track ended, ordered by start tick. Their
+    /// duration runs to the end of the track and their release velocity is 0
+    pub unterminated: Vec<Note>,
+}
+
+impl TrackChunk {
+    /// Pairs the track's `NoteOn` and `NoteOff` events into notes. A `NoteOn` with velocity 0 is
+    /// treated as a note off
+    pub fn notes(&self, matching: NoteMatching) -> Notes {
+        let mut sounding: BTreeMap<(u8, u8), VecDeque<(u64, u8)>> = BTreeMap::new();
+        let mut result = Notes::default();
+        let mut end_tick = 0;
+
+        for (tick, event) in self.absolute_events() {
+            end_tick = tick;
+
+            let (channel, meta, on) = match event {
+                Event::MidiEvent(MidiEvent::NoteOn(channel, meta)) => {
+                    (channel.get(), meta, meta.velocity().get() != 0)
+                }
+                Event::MidiEvent(MidiEvent::NoteOff(channel, meta)) => (channel.get(), meta, false),
+                Event::MetaEvent(MetaEvent::EndOfTrack) => break,
+                _ => continue,
+            };
+
+            let queue = sounding.entry((channel, meta.key().get())).or_default();
+            if on {
+                queue.push_back((tick, meta.velocity().get()));
+                continue;
+            }
+
+            let started = match matching {
+                NoteMatching::Fifo => queue.pop_front(),
+                NoteMatching::Lifo => queue.pop_back(),
+            };
+
+            if let Some((start_tick, velocity)) = started {
+                result.notes.push(Note {
+                    channel,
+                    key: meta.key().get(),
+                    velocity,
+                    start_tick,
+                    duration_ticks: tick - start_tick,
+                    release_velocity: meta.velocity().get(),
+                });
+            }
+        }
+
+        for ((channel, key), queue) in sounding {
+            for (start_tick, velocity) in queue {
+                result.unterminated.push(Note {
+                    channel,
+                    key,
+                    velocity,
+                    start_tick,
+                    duration_ticks: end_tick - start_tick,
+                    release_velocity: 0,
+                });
+            }
+        }
+
+        result.notes.sort_by_key(|note| note.start_tick);
+        result.unterminated.sort_by_key(|note| note.start_tick);
+
+        result
+    }
+
+    /// Builds a track from a list of notes, writing a `NoteOn` and `NoteOff` for each and ending
+    /// the track at the last note off. At the same tick, note offs are placed before note ons so
+    /// repeated notes don't cut each other off. Fails if a note's channel, key or velocities are
+    /// out of range, its velocity is 0, or consecutive events are too far apart to encode
+    pub fn from_notes(notes: &[Note]) -> Result<TrackChunk, OutOfRange> {
+        let mut events = Vec::with_capacity(notes.len() * 2 + 1);
+        let mut end_tick = 0;
+
+        for note in notes {
+            OutOfRange::check("note velocity", note.velocity, 1, 0x7F)?;
+            let on = MidiEvent::note_on(note.channel, note.key, note.velocity)?;
+            let off = MidiEvent::note_off(note.channel, note.key, note.release_velocity)?;
+
+            // Zero length notes must still be turned on before they're turned off
+            let off_order = if note.duration_ticks == 0 { 2 } else { 0 };
+
+            events.push((note.start_tick, 1, Event::MidiEvent(on)));
+            events.push((note.end_tick(), off_order, Event::MidiEvent(off)));
+            end_tick = end_tick.max(note.end_tick());
+        }
+
+        events.sort_by_key(|(tick, order, _)| (*tick, *order));
+        events.push((end_tick, 3, Event::MetaEvent(MetaEvent::EndOfTrack)));
+
+        TrackChunk::from_absolute_events(events.into_iter().map(|(tick, _, event)| (tick, event)))
+    }
+}
+
+impl Midi {
+    /// Pairs the notes of every track, see [`TrackChunk::notes`]. Notes are only paired within
+    /// a track, and the result is indexed by track
+    pub fn notes(&self, matching: NoteMatching) -> Vec<Notes> {
+        self.tracks
+            .iter()
+            .map(|track| track.notes(matching))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk::track::TrackChunk, writer::MidiWriteable};
+
+    use super::{Note, NoteMatching};
+
+    /// Two overlapping notes on the same key, the second struck before the first is released,
+    /// followed by a note left sounding
+    const OVERLAPPING: &[u8] = &[
+        0x00, 0x90, 0x3C, 0x40, // Note on at 0
+        0x10, 0x90, 0x3C, 0x50, // Note on at 16
+        0x10, 0x80, 0x3C, 0x20, // Note off at 32
+        0x10, 0x90, 0x3C, 0x00, // Note on with velocity 0 at 48
+        0x00, 0x91, 0x40, 0x60, // Note on at 48, channel 1
+        0x10, 0xFF, 0x2F, 0x00, // End of track at 64
+    ];
+
+    #[test]
+    fn fifo_matching_pairs_earliest_note() {
+        let track = TrackChunk::try_from(OVERLAPPING.to_vec()).expect("Parse track");
+        let notes = track.notes(NoteMatching::Fifo);
+
+        assert_eq!(
+            notes.notes,
+            vec![
+                Note {
+                    channel: 0,
+                    key: 0x3C,
+                    velocity: 0x40,
+                    start_tick: 0,
+                    duration_ticks: 32,
+                    release_velocity: 0x20
+                },
+                Note {
+                    channel: 0,
+                    key: 0x3C,
+                    velocity: 0x50,
+                    start_tick: 16,
+                    duration_ticks: 32,
+                    release_velocity: 0
+                },
+            ]
+        );
+        assert_eq!(
+            notes.unterminated,
+            vec![Note {
+                channel: 1,
+                key: 0x40,
+                velocity: 0x60,
+                start_tick: 48,
+                duration_ticks: 16,
+                release_velocity: 0
+            }]
+        )
+    }
+
+    #[test]
+    fn lifo_matching_pairs_latest_note() {
+        let track = TrackChunk::try_from(OVERLAPPING.to_vec()).expect("Parse track");
+        let notes = track.notes(NoteMatching::Lifo);
+
+        let spans: Vec<_> = notes
+            .notes
+            .iter()
+            .map(|note| (note.start_tick, note.duration_ticks))
+            .collect();
+        assert_eq!(spans, vec![(0, 48), (16, 16)])
+    }
+
+    #[test]
+    fn notes_build_back_into_a_track() {
+        let notes = [
+            Note {
+                channel: 0,
+                key: 0x3C,
+                velocity: 0x40,
+                start_tick: 0,
+                duration_ticks: 16,
+                release_velocity: 0x40,
+            },
+            Note {
+                channel: 0,
+                key: 0x3C,
+                velocity: 0x40,
+                start_tick: 16,
+                duration_ticks: 16,
+                release_velocity: 0x40,
+            },
+        ];
+
+        let track = TrackChunk::from_notes(&notes).expect("Build track from notes");
+        assert_eq!(
+            track.clone().to_midi_bytes(),
+            vec![
+                0x00, 0x90, 0x3C, 0x40, //
+                0x10, 0x80, 0x3C, 0x40, //
+                0x00, 0x90, 0x3C, 0x40, //
+                0x10, 0x80, 0x3C, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(track.notes(NoteMatching::Fifo).notes, notes.to_vec());
+
+        let silent = Note {
+            velocity: 0,
+            ..notes[0]
+        };
+        assert!(TrackChunk::from_notes(&[silent]).is_err())
+    }
+}
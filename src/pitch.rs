@@ -0,0 +1,393 @@
+//! Pitch names, parsing and frequencies for note keys
+
+use core::str::FromStr;
+
+use crate::{
+    chunk::{
+        track::{
+            meta::{KeySignature, MetaEvent},
+            value::U7,
+            Event,
+        },
+        OutOfRange,
+    },
+    Midi,
+};
+
+/// Octave number of middle C (key 60) in scientific pitch notation. Some manufacturers number
+/// it 3 instead
+pub const SCIENTIFIC_MIDDLE_C_OCTAVE: i8 = 4;
+
+/// Standard concert pitch of A4 (key 69) in hertz
+pub const A4_FREQUENCY: f64 = 440.0;
+
+/// Frequency ratio of each semitone above a note within an octave, `2^(n/12)`
+const SEMITONE_RATIOS: [f64; 12] = [
+    1.0,
+    1.0594630943592953,
+    1.122462048309373,
+    1.189207115002721,
+    1.2599210498948732,
+    1.3348398541700344,
+    core::f64::consts::SQRT_2,
+    1.4983070768766815,
+    1.5874010519681994,
+    1.681792830507429,
+    1.7817974362806785,
+    1.8877486253633868,
+];
+
+/// Letter and accidental of each pitch class when spelled with sharps
+const SHARP_NAMES: [(char, Accidental); 12] = [
+    ('C', Accidental::Natural),
+    ('C', Accidental::Sharp),
+    ('D', Accidental::Natural),
+    ('D', Accidental::Sharp),
+    ('E', Accidental::Natural),
+    ('F', Accidental::Natural),
+    ('F', Accidental::Sharp),
+    ('G', Accidental::Natural),
+    ('G', Accidental::Sharp),
+    ('A', Accidental::Natural),
+    ('A', Accidental::Sharp),
+    ('B', Accidental::Natural),
+];
+
+/// Letter and accidental of each pitch class when spelled with flats
+const FLAT_NAMES: [(char, Accidental); 12] = [
+    ('C', Accidental::Natural),
+    ('D', Accidental::Flat),
+    ('D', Accidental::Natural),
+    ('E', Accidental::Flat),
+    ('E', Accidental::Natural),
+    ('F', Accidental::Natural),
+    ('G', Accidental::Flat),
+    ('G', Accidental::Natural),
+    ('A', Accidental::Flat),
+    ('A', Accidental::Natural),
+    ('B', Accidental::Flat),
+    ('B', Accidental::Natural),
+];
+
+/// Whether black keys are spelled as sharps or flats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Spelling {
+    /// Spell black keys as the sharp of the key below, such as C#
+    #[default]
+    Sharps,
+    /// Spell black keys as the flat of the key above, such as Db
+    Flats,
+}
+
+impl From<KeySignature> for Spelling {
+    /// Spells with flats in keys with flats in their signature, and with sharps otherwise
+    fn from(value: KeySignature) -> Self {
+        if value.sharps_flats() < 0 {
+            Self::Flats
+        } else {
+            Self::Sharps
+        }
+    }
+}
+
+impl Midi {
+    /// Gets the spelling for the key signature in effect at a tick, gathering key signatures
+    /// from every track. Spells with sharps before the first key signature
+    pub fn spelling_at(&self, tick: u64) -> Spelling {
+        self.merged_events()
+            .take_while(|merged| merged.tick <= tick)
+            .filter_map(|merged| match merged.event {
+                Event::MetaEvent(MetaEvent::KeySignature(key)) => Some(Spelling::from(*key)),
+                _ => None,
+            })
+            .last()
+            .unwrap_or_default()
+    }
+}
+
+/// An accidental applied to a note letter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Accidental {
+    /// Lowers the letter by a semitone
+    Flat,
+    /// Leaves the letter unchanged
+    Natural,
+    /// Raises the letter by a semitone
+    Sharp,
+}
+
+/// A pitch spelled as a letter, accidental and octave, displayed like `C#4` or `Eb3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PitchName {
+    /// The note letter, from `A` to `G`
+    pub letter: char,
+    /// The accidental applied to the letter
+    pub accidental: Accidental,
+    /// The octave number
+    pub octave: i16,
+}
+
+impl core::fmt::Display for PitchName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let accidental = match self.accidental {
+            Accidental::Flat => "b",
+            Accidental::Natural => "",
+            Accidental::Sharp => "#",
+        };
+        write![f, "{}{}{}", self.letter, accidental, self.octave]
+    }
+}
+
+/// Error type for parsing a pitch name
+#[derive(Debug, Clone, PartialEq)]
+pub enum PitchParseError {
+    /// The name doesn't start with a note letter from `A` to `G`
+    InvalidLetter,
+    /// The letter and accidentals aren't followed by an octave number
+    InvalidOctave,
+    /// The named pitch is outside the range of MIDI keys
+    OutOfRange(OutOfRange),
+}
+
+impl core::error::Error for PitchParseError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::OutOfRange(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl core::fmt::Display for PitchParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidLetter => write![f, "Pitch name doesn't start with a note letter"],
+            Self::InvalidOctave => write![f, "Pitch name doesn't end with an octave number"],
+            Self::OutOfRange(_) => write![f, "Pitch is outside the range of MIDI keys"],
+        }
+    }
+}
+
+impl From<OutOfRange> for PitchParseError {
+    fn from(value: OutOfRange) -> Self {
+        Self::OutOfRange(value)
+    }
+}
+
+/// The pitch of a MIDI key. Displays and parses in scientific pitch notation, with middle C as
+/// `C4`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pitch(U7);
+
+impl Pitch {
+    /// Middle C, key 60
+    pub const MIDDLE_C: Self = Self(U7::saturating(60));
+    /// The A above middle C, key 69, which tuning is usually referenced to
+    pub const A4: Self = Self(U7::saturating(69));
+
+    /// Creates the pitch of a key
+    pub const fn new(key: U7) -> Self {
+        Self(key)
+    }
+
+    /// Gets the key
+    pub const fn key(self) -> U7 {
+        self.0
+    }
+
+    /// Gets the pitch class, the number of semitones above C from 0 to 11
+    pub fn pitch_class(self) -> u8 {
+        self.0.get() % 12
+    }
+
+    /// Gets the octave number, given the octave number of middle C
+    pub fn octave(self, middle_c_octave: i8) -> i16 {
+        (self.0.get() / 12) as i16 - 5 + middle_c_octave as i16
+    }
+
+    /// Spells the pitch, given how to spell black keys and the octave number of middle C
+    pub fn name(self, spelling: Spelling, middle_c_octave: i8) -> PitchName {
+        let names = match spelling {
+            Spelling::Sharps => &SHARP_NAMES,
+            Spelling::Flats => &FLAT_NAMES,
+        };
+        let (letter, accidental) = names[self.pitch_class() as usize];
+
+        PitchName {
+            letter,
+            accidental,
+            octave: self.octave(middle_c_octave),
+        }
+    }
+
+    /// Parses a pitch name such as `C#4`, `Eb3` or `c-1`, given the octave number of middle C.
+    /// The letter is case insensitive and may be followed by any number of `#` or `b`
+    /// accidentals, or their `♯` and `♭` symbols
+    pub fn parse(name: &str, middle_c_octave: i8) -> Result<Self, PitchParseError> {
+        let mut chars = name.chars();
+        let letter = chars.next().ok_or(PitchParseError::InvalidLetter)?;
+        let class = SHARP_NAMES
+            .iter()
+            .position(|(name, accidental)| {
+                *accidental == Accidental::Natural && letter.eq_ignore_ascii_case(name)
+            })
+            .ok_or(PitchParseError::InvalidLetter)? as i64;
+
+        let rest = chars.as_str();
+        let octave_start = rest
+            .find(|c: char| !matches!(c, '#' | 'b' | '♯' | '♭'))
+            .unwrap_or(rest.len());
+        let (accidentals, octave) = rest.split_at(octave_start);
+        let shift: i64 = accidentals
+            .chars()
+            .map(|c| if matches!(c, '#' | '♯') { 1 } else { -1 })
+            .sum();
+
+        // Any octave that fits in an `i16` keeps the key arithmetic far from overflowing
+        let octave: i16 = octave.parse().map_err(|_| PitchParseError::InvalidOctave)?;
+        let key = (octave as i64 - middle_c_octave as i64 + 5) * 12 + class + shift;
+
+        if !(0..=0x7F).contains(&key) {
+            return Err(OutOfRange {
+                field: "key",
+                value: key,
+            }
+            .into());
+        }
+
+        Ok(Self(U7::saturating(key as u8)))
+    }
+
+    /// Gets the pitch's frequency in hertz in twelve-tone equal temperament, given the frequency
+    /// of A4
+    pub fn frequency(self, a4: f64) -> f64 {
+        let semitones = self.0.get() as i32 - Self::A4.0.get() as i32;
+        let octaves = semitones.div_euclid(12);
+        let ratio = SEMITONE_RATIOS[semitones.rem_euclid(12) as usize];
+
+        let octave_scale = if octaves >= 0 {
+            (1u32 << octaves) as f64
+        } else {
+            1.0 / (1u32 << -octaves) as f64
+        };
+
+        a4 * ratio * octave_scale
+    }
+}
+
+impl From<U7> for Pitch {
+    fn from(value: U7) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Pitch> for U7 {
+    fn from(value: Pitch) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for Pitch {
+    type Err = PitchParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, SCIENTIFIC_MIDDLE_C_OCTAVE)
+    }
+}
+
+impl core::fmt::Display for Pitch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write![
+            f,
+            "{}",
+            self.name(Spelling::Sharps, SCIENTIFIC_MIDDLE_C_OCTAVE)
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use crate::{
+        chunk::track::{
+            meta::{KeySignature, MetaEvent, Mode},
+            value::U7,
+            TrackChunk,
+        },
+        Midi,
+    };
+
+    use super::{Pitch, PitchParseError, Spelling, A4_FREQUENCY, SCIENTIFIC_MIDDLE_C_OCTAVE};
+
+    #[test]
+    fn pitches_are_named_with_the_key_signatures_accidentals() {
+        let pitch = Pitch::new(U7::new(61).unwrap());
+        assert_eq!(pitch.to_string(), "C#4");
+
+        let f_major = Spelling::from(KeySignature::new(-1, Mode::Major).unwrap());
+        assert_eq!(pitch.name(f_major, 3).to_string(), "Db3");
+        assert_eq!(Pitch::new(U7::MIN).to_string(), "C-1");
+        assert_eq!(Pitch::new(U7::MAX).to_string(), "G9");
+
+        let track = TrackChunk::builder()
+            .event(
+                96,
+                MetaEvent::KeySignature(KeySignature::new(-3, Mode::Minor).unwrap()),
+            )
+            .build()
+            .unwrap();
+        let midi = Midi::builder().track(track).build().unwrap();
+        assert_eq!(midi.spelling_at(95), Spelling::Sharps);
+        assert_eq!(midi.spelling_at(96), Spelling::Flats);
+    }
+
+    #[test]
+    fn pitch_names_parse_back_into_keys() {
+        for key in 0..=0x7F {
+            let pitch = Pitch::new(U7::new(key).unwrap());
+            for spelling in [Spelling::Sharps, Spelling::Flats] {
+                let name = pitch.name(spelling, 3).to_string();
+                assert_eq!(Pitch::parse(&name, 3), Ok(pitch), "{name}");
+            }
+        }
+
+        assert_eq!("eb3".parse::<Pitch>().map(|p| p.key().get()), Ok(51));
+        assert_eq!("B#3".parse::<Pitch>(), Ok(Pitch::MIDDLE_C));
+        assert_eq!("H4".parse::<Pitch>(), Err(PitchParseError::InvalidLetter));
+        assert_eq!("C#".parse::<Pitch>(), Err(PitchParseError::InvalidOctave));
+        assert!(matches!(
+            Pitch::parse("G#9", SCIENTIFIC_MIDDLE_C_OCTAVE),
+            Err(PitchParseError::OutOfRange(_))
+        ));
+        assert_eq!(
+            Pitch::parse("C922337203685477580", 4),
+            Err(PitchParseError::InvalidOctave)
+        );
+        assert!(matches!(
+            Pitch::parse("C32767", i8::MIN),
+            Err(PitchParseError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn extreme_middle_c_octaves_do_not_overflow() {
+        let pitch = Pitch::new(U7::MAX);
+        assert_eq!(pitch.octave(i8::MAX), 132);
+        assert_eq!(Pitch::new(U7::MIN).octave(i8::MIN), -133);
+
+        let name = pitch.name(Spelling::Sharps, i8::MAX).to_string();
+        assert_eq!(name, "G132");
+        assert_eq!(Pitch::parse(&name, i8::MAX), Ok(pitch));
+    }
+
+    #[test]
+    fn frequencies_follow_equal_temperament() {
+        assert_eq!(Pitch::A4.frequency(A4_FREQUENCY), 440.0);
+        assert_eq!(
+            Pitch::new(U7::new(81).unwrap()).frequency(A4_FREQUENCY),
+            880.0
+        );
+        assert_eq!(Pitch::new(U7::new(45).unwrap()).frequency(432.0), 108.0);
+        assert!((Pitch::MIDDLE_C.frequency(A4_FREQUENCY) - 261.625_565).abs() < 1e-5);
+        assert!((Pitch::new(U7::MIN).frequency(A4_FREQUENCY) - 8.175_799).abs() < 1e-5);
+    }
+}
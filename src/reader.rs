@@ -1,17 +1,30 @@
 //! MIDI file reader trait, allows for in memory byte spans to be read or files
 
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::{
-    convert::Infallible,
     fs::File,
     io::{BufReader, Read},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
-use crate::Chunk;
+#[cfg(feature = "std")]
+use crate::rmid::{is_rmid, DATA};
+
+use crate::{
+    chunk::{
+        chunk_types::TRACK_DATA_CHUNK, track::TrackEvents, ChunkParseError, ErrorLocation,
+        ParsedChunk,
+    },
+    rmid::{unwrap_rmid, RmidError},
+    Chunk,
+};
 
 /// Trait that allows certain amount of bytes to be yielded by an iterator
 pub trait Yieldable<T> {
-    /// Gets a certain number of elements while advancing the iterator
+    /// Gets up to a certain number of elements while advancing the iterator. Only the space the
+    /// iterator is known to fill is reserved up front, as `n` often comes from a length read out
+    /// of the data itself
     fn get(&mut self, n: usize) -> Vec<T>;
 }
 
@@ -21,7 +34,7 @@ where
 {
     #[allow(if_let_rescope)]
     fn get(&mut self, n: usize) -> Vec<ITER::Item> {
-        let mut elements = Vec::with_capacity(n);
+        let mut elements = Vec::with_capacity(n.min(self.size_hint().0));
         for _ in 0..n {
             if let Some(item) = self.next() {
                 elements.push(item);
@@ -33,29 +46,79 @@ where
     }
 }
 
+/// Error from a [`MidiStream`] that couldn't read a whole chunk
+#[derive(Debug)]
+pub enum StreamError {
+    /// The underlying reader failed
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The stream ended partway through a chunk
+    Truncated {
+        /// Number of bytes the chunk header or payload needed
+        needed: usize,
+        /// Number of bytes that were available
+        available: usize,
+    },
+}
+
+impl core::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => Some(e),
+            Self::Truncated { .. } => None,
+        }
+    }
+}
+impl core::fmt::Display for StreamError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => write![f, "Failed to read MIDI stream: {e}"],
+            Self::Truncated { needed, available } => write![
+                f,
+                "MIDI stream ended early, needed {needed} bytes but only {available} were available"
+            ],
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl From<std::io::Error> for StreamError {
+    fn from(f: std::io::Error) -> Self {
+        Self::Io(f)
+    }
+}
+
 /// Trait for reading sequential chunks from a MIDI stream
 pub trait MidiStream {
     /// Reads the next chunk from the sequence along with its associated data.
     ///
     /// # Returns
-    /// - `Some((Chunk, Vec<u8>))`: If a complete chunk and its data are successfully read.
-    /// - `None`: If there isn't enough data left to read a full chunk or its payload.
+    /// - `Ok(Some((Chunk, Vec<u8>)))`: If a complete chunk and its data are successfully read.
+    /// - `Ok(None)`: If the stream ended cleanly before the next chunk.
     ///
     /// # Errors
-    /// This method will fail silently by returning `None` if the stream does not contain enough
-    /// data to read a full chunk header or its associated payload.
-    fn read_chunk_data_pair(&mut self) -> Option<(Chunk, Vec<u8>)>;
+    /// Returns [`StreamError::Truncated`] if the stream ends partway through a chunk header or
+    /// its payload, and [`StreamError::Io`] if the underlying source fails.
+    fn read_chunk_data_pair(&mut self) -> Result<Option<(Chunk, Vec<u8>)>, StreamError>;
 }
 
 impl<MIDI> MidiStream for MIDI
 where
     MIDI: Iterator<Item = u8>,
 {
-    fn read_chunk_data_pair(&mut self) -> Option<(Chunk, Vec<u8>)> {
+    fn read_chunk_data_pair(&mut self) -> Result<Option<(Chunk, Vec<u8>)>, StreamError> {
         let chunk_packet = self.get(8);
 
-        if chunk_packet.len() != 8 {
-            return None;
+        match chunk_packet.len() {
+            0 => return Ok(None),
+            8 => {}
+            available => {
+                return Err(StreamError::Truncated {
+                    needed: 8,
+                    available,
+                })
+            }
         }
 
         // UNWRAP Safety: We verify the chunk packet is 8 bytes before
@@ -65,10 +128,303 @@ where
         let data = self.get(chunk.len());
 
         if data.len() != chunk.len() {
+            return Err(StreamError::Truncated {
+                needed: chunk.len(),
+                available: data.len(),
+            });
+        }
+
+        Ok(Some((chunk, data)))
+    }
+}
+
+/// A [`MidiStream`] over any [`std::io::Read`] implementor, such as files, stdin, sockets or
+/// cursors. I/O errors are returned rather than treated as the end of the stream. A RIFF `RMID`
+/// container is detected from its header and only its wrapped MIDI data is read
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoStream<READ: Read> {
+    /// The wrapped reader
+    reader: READ,
+    /// Bytes read while checking for a RIFF `RMID` header that belong to the MIDI data
+    buffered: Vec<u8>,
+    /// Bytes left in the `data` chunk of an unwrapped RIFF `RMID` container
+    remaining: Option<u64>,
+    /// Whether the start of the stream has been checked for a RIFF `RMID` header
+    started: bool,
+}
+
+#[cfg(feature = "std")]
+impl<READ: Read> IoStream<READ> {
+    /// Wraps a reader. Reads are made in chunk sized pieces, so wrapping an unbuffered source in
+    /// a [`BufReader`] isn't necessary
+    pub fn new(reader: READ) -> Self {
+        Self {
+            reader,
+            buffered: vec![],
+            remaining: None,
+            started: false,
+        }
+    }
+
+    /// Consumes the stream, returning the wrapped reader. Up to 12 bytes read ahead while
+    /// checking for a RIFF `RMID` header may not have been returned as chunks yet
+    pub fn into_inner(self) -> READ {
+        self.reader
+    }
+
+    /// Fills as much of the buffer as the reader allows, returning how many bytes were read.
+    /// Reads stop at the end of an unwrapped RIFF `RMID` container's `data` chunk
+    fn fill(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        let buffered = self.buffered.len().min(buf.len());
+        buf[..buffered].copy_from_slice(&self.buffered[..buffered]);
+        self.buffered.drain(..buffered);
+
+        let end = match self.remaining {
+            Some(remaining) => buf.len().min(buffered.saturating_add(remaining as usize)),
+            None => buf.len(),
+        };
+
+        let mut read = buffered;
+        while read < end {
+            match self.reader.read(&mut buf[read..end]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= (read - buffered) as u64;
+        }
+        Ok(read)
+    }
+
+    /// Checks the start of the stream for a RIFF `RMID` header, skipping to the wrapped MIDI
+    /// data if there is one. Bytes that turn out to be MIDI data are kept to be read again. A
+    /// malformed container fails with [`std::io::ErrorKind::InvalidData`], wrapping the
+    /// [`RmidError`]
+    fn unwrap_rmid(&mut self) -> Result<(), std::io::Error> {
+        let invalid = |e: RmidError| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+
+        let mut header = [0; 12];
+        let read = self.fill(&mut header)?;
+        if !is_rmid(&header[..read]) {
+            self.buffered = header[..read].to_vec();
+            return Ok(());
+        }
+
+        loop {
+            let mut chunk_header = [0; 8];
+            if self.fill(&mut chunk_header)? < chunk_header.len() {
+                return Err(invalid(RmidError::MissingData));
+            }
+
+            let (id, len) = chunk_header.split_at(4);
+            // UNWRAP Safety: The chunk header was split into two 4 byte halves
+            let len = u32::from_le_bytes(len.try_into().unwrap()) as u64;
+            if id == DATA {
+                self.remaining = Some(len);
+                return Ok(());
+            }
+
+            // Chunks are padded to an even length
+            let padded = len + len % 2;
+            let skipped =
+                std::io::copy(&mut (&mut self.reader).take(padded), &mut std::io::sink())?;
+            if skipped < padded {
+                return Err(invalid(RmidError::Truncated));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<READ: Read> MidiStream for IoStream<READ> {
+    fn read_chunk_data_pair(&mut self) -> Result<Option<(Chunk, Vec<u8>)>, StreamError> {
+        if !self.started {
+            self.started = true;
+            self.unwrap_rmid()?;
+        }
+
+        let mut chunk_packet = [0; 8];
+
+        match self.fill(&mut chunk_packet)? {
+            0 => return Ok(None),
+            8 => {}
+            available => {
+                return Err(StreamError::Truncated {
+                    needed: 8,
+                    available,
+                })
+            }
+        }
+
+        let chunk: Chunk = u64::from_be_bytes(chunk_packet).into();
+
+        // Grow the buffer as data arrives rather than trusting the declared length up front
+        let mut data = vec![];
+        let mut piece = [0; 4096];
+        while data.len() < chunk.len() {
+            let wanted = piece.len().min(chunk.len() - data.len());
+            let read = self.fill(&mut piece[..wanted])?;
+            data.extend_from_slice(&piece[..read]);
+            if read < wanted {
+                break;
+            }
+        }
+
+        if data.len() != chunk.len() {
+            return Err(StreamError::Truncated {
+                needed: chunk.len(),
+                available: data.len(),
+            });
+        }
+
+        Ok(Some((chunk, data)))
+    }
+}
+
+/// Splits a chunk header and its payload off the front of a buffer
+pub(crate) fn split_chunk(bytes: &[u8]) -> Result<(Chunk, &[u8]), StreamError> {
+    if bytes.len() < 8 {
+        return Err(StreamError::Truncated {
+            needed: 8,
+            available: bytes.len(),
+        });
+    }
+
+    // UNWRAP Safety: We verify at least 8 bytes remain before
+    let chunk: Chunk = u64::from_be_bytes(bytes[..8].try_into().unwrap()).into();
+    let data = &bytes[8..];
+
+    if data.len() < chunk.len() {
+        return Err(StreamError::Truncated {
+            needed: chunk.len(),
+            available: data.len(),
+        });
+    }
+
+    Ok((chunk, &data[..chunk.len()]))
+}
+
+/// Lazily walks the chunks of a MIDI file held in memory. Chunk payloads are borrowed and only
+/// decoded on request, so tracks that aren't needed are never parsed
+#[derive(Debug, Clone)]
+pub struct LazyChunks<'a> {
+    /// The whole file
+    bytes: &'a [u8],
+    /// Offset of the next chunk
+    pos: usize,
+    /// Index of the next chunk
+    index: usize,
+    /// Set once the file is exhausted or an error was yielded
+    done: bool,
+}
+
+impl<'a> LazyChunks<'a> {
+    /// Creates an iterator over the chunks of a MIDI file
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            index: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for LazyChunks<'a> {
+    type Item = Result<LazyChunk<'a>, ChunkParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.pos >= self.bytes.len() {
             return None;
         }
 
-        Some((chunk, data))
+        match split_chunk(&self.bytes[self.pos..]) {
+            Ok((chunk, data)) => {
+                let lazy = LazyChunk {
+                    chunk,
+                    data,
+                    index: self.index,
+                    offset: self.pos,
+                };
+                self.pos += 8 + data.len();
+                self.index += 1;
+                Some(Ok(lazy))
+            }
+            Err(source) => {
+                self.done = true;
+                let location = ErrorLocation {
+                    offset: self.pos,
+                    chunk: self.index,
+                    ..Default::default()
+                };
+                Some(Err(ChunkParseError::Stream { location, source }))
+            }
+        }
+    }
+}
+
+impl core::iter::FusedIterator for LazyChunks<'_> {}
+
+/// A chunk found by [`LazyChunks`] whose payload hasn't been decoded yet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LazyChunk<'a> {
+    /// The chunk's type and length
+    pub chunk: Chunk,
+    /// The chunk's raw payload
+    pub data: &'a [u8],
+    /// Index of the chunk within the file
+    pub index: usize,
+    /// Absolute offset of the chunk's header within the file
+    pub offset: usize,
+}
+
+impl<'a> LazyChunk<'a> {
+    /// Returns true if this is a track chunk
+    pub fn is_track(&self) -> bool {
+        self.chunk.chunk_type == TRACK_DATA_CHUNK
+    }
+
+    /// Lazily decodes the chunk's events if it is a track chunk. Error locations are absolute
+    /// within the file
+    pub fn track_events(&self) -> Option<TrackEvents<'a>> {
+        self.is_track()
+            .then(|| TrackEvents::in_chunk(self.data, self.index, self.offset + 8))
+    }
+
+    /// Fully decodes the chunk
+    pub fn parse(&self) -> Result<ParsedChunk, ChunkParseError> {
+        ParsedChunk::try_from((self.chunk, self.data.to_vec()))
+            .map_err(|e| e.in_chunk(self.index, self.offset + 8))
+    }
+}
+
+/// Iterator adapter that counts how many items have been taken
+pub(crate) struct Counted<ITER> {
+    /// The wrapped iterator
+    inner: ITER,
+    /// Number of items taken so far
+    pub(crate) count: usize,
+}
+
+impl<ITER> Counted<ITER> {
+    /// Wraps an iterator
+    pub(crate) fn new(inner: ITER) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<ITER: Iterator> Iterator for Counted<ITER> {
+    type Item = ITER::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        self.count += 1;
+        Some(item)
     }
 }
 
@@ -83,30 +439,138 @@ pub trait MidiReadable {
 /// Wrapper struct to allow passing Vec<u8> to MidiReadable trait
 pub struct MidiData(Vec<u8>);
 
+impl From<Vec<u8>> for MidiData {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
 impl MidiReadable for MidiData {
-    type Error = Infallible;
+    type Error = RmidError;
     fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
-        Ok(self.0.into_iter())
+        self.0.get_midi_bytes()
     }
 }
 
-impl<PATH> MidiReadable for PATH
-where
-    PATH: AsRef<Path>,
-{
+/// Reads MIDI bytes held in memory. RIFF `RMID` data is detected and the wrapped MIDI data is
+/// returned, failing if the container is malformed
+impl MidiReadable for Vec<u8> {
+    type Error = RmidError;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        Ok(unwrap_rmid(self)?.into_iter())
+    }
+}
+
+/// Reads MIDI bytes held in memory. RIFF `RMID` data is detected and the wrapped MIDI data is
+/// returned, failing if the container is malformed
+impl MidiReadable for &[u8] {
+    type Error = RmidError;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        self.to_vec().get_midi_bytes()
+    }
+}
+
+/// Reads a MIDI file from a path. RIFF `RMID` files are detected and the wrapped MIDI data is
+/// returned. A malformed container fails with [`std::io::ErrorKind::InvalidData`], wrapping the
+/// [`RmidError`]
+#[cfg(feature = "std")]
+fn read_midi_file(path: &Path) -> Result<alloc::vec::IntoIter<u8>, std::io::Error> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    let bytes =
+        unwrap_rmid(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    Ok(bytes.into_iter())
+}
+
+/// Reads a MIDI file from any path-like value, such as an [`OsString`](std::ffi::OsString) or a
+/// `Cow<Path>`. Path types are otherwise only readable directly if they have their own
+/// [`MidiReadable`] implementation, as a blanket one would overlap with the in-memory readers
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MidiFile<PATH>(pub PATH);
+
+#[cfg(feature = "std")]
+impl<PATH: AsRef<Path>> MidiReadable for MidiFile<PATH> {
+    type Error = std::io::Error;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        read_midi_file(self.0.as_ref())
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiReadable for &Path {
+    type Error = std::io::Error;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        read_midi_file(self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiReadable for PathBuf {
+    type Error = std::io::Error;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        read_midi_file(&self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiReadable for &PathBuf {
+    type Error = std::io::Error;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        read_midi_file(self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiReadable for &str {
+    type Error = std::io::Error;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        read_midi_file(self.as_ref())
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiReadable for String {
     type Error = std::io::Error;
     fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
-        let path = self.as_ref();
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        Ok(reader.bytes().filter_map(Result::ok))
+        read_midi_file(self.as_ref())
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiReadable for &String {
+    type Error = std::io::Error;
+    fn get_midi_bytes(self) -> Result<impl Iterator<Item = u8>, Self::Error> {
+        read_midi_file(self.as_ref())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::MidiReadable;
+    #[cfg(feature = "std")]
+    use std::io::{Cursor, Read};
+
+    use crate::{
+        chunk::{track::TrackChunk, ChunkParseError, ParsedChunk},
+        RawMidi,
+    };
 
+    #[cfg(feature = "std")]
+    use super::{IoStream, MidiFile, MidiReadable, MidiStream};
+    use super::{LazyChunk, StreamError, Yieldable};
+
+    #[test]
+    fn yielding_past_the_end_only_reserves_what_is_there() {
+        let elements = [1u8, 2, 3].into_iter().get(usize::MAX);
+
+        assert_eq!(elements, vec![1, 2, 3]);
+        assert!(elements.capacity() < 16);
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn midi_files_stream() {
         let path = "test/run.mid";
@@ -114,4 +578,160 @@ mod tests {
 
         assert!(data.is_ok())
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_stream_reads_the_same_chunks_as_bytes() {
+        let bytes = std::fs::read("test/test.mid").expect("Read test file");
+
+        let from_io = RawMidi::try_from_midi_stream(IoStream::new(Cursor::new(bytes.clone())))
+            .expect("Parse from reader");
+        let from_bytes = RawMidi::try_from_midi_stream(bytes.as_slice().get_midi_bytes().unwrap())
+            .expect("Parse from bytes");
+
+        assert_eq!(from_io, from_bytes)
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn truncation_and_io_failures_are_distinct() {
+        let bytes = [b'M', b'T', b'r', b'k', 0, 0, 0, 4, 0x00, 0xFF];
+
+        let mut stream = IoStream::new(Cursor::new(bytes));
+        assert!(matches!(
+            stream.read_chunk_data_pair(),
+            Err(StreamError::Truncated {
+                needed: 4,
+                available: 2
+            })
+        ));
+
+        let mut stream = bytes[..5].iter().copied();
+        assert!(matches!(
+            stream.read_chunk_data_pair(),
+            Err(StreamError::Truncated {
+                needed: 8,
+                available: 5
+            })
+        ));
+
+        /// Reader that always fails
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disconnected"))
+            }
+        }
+
+        let mut stream = IoStream::new(Failing);
+        assert!(matches!(
+            stream.read_chunk_data_pair(),
+            Err(StreamError::Io(_))
+        ));
+
+        let mut stream = IoStream::new(Cursor::new([]));
+        assert!(matches!(stream.read_chunk_data_pair(), Ok(None)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_stream_unwraps_rmid() {
+        let bytes = std::fs::read("test/test.mid").expect("Read test file");
+        let expected = RawMidi::try_from_midi_stream(bytes.clone().into_iter()).expect("Parse");
+
+        /// Appends a RIFF chunk, padded to an even length
+        fn riff_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+            out.extend(id);
+            out.extend((data.len() as u32).to_le_bytes());
+            out.extend(data);
+            if data.len() % 2 == 1 {
+                out.push(0);
+            }
+        }
+
+        let mut body = b"RMID".to_vec();
+        riff_chunk(&mut body, b"JUNK", &[1, 2, 3]);
+        riff_chunk(&mut body, b"data", &bytes);
+        riff_chunk(&mut body, b"LIST", b"INFOINAM\x02\x00\x00\x00A\x00");
+        let mut rmid = vec![];
+        riff_chunk(&mut rmid, b"RIFF", &body);
+
+        let from_io = RawMidi::try_from_midi_stream(IoStream::new(Cursor::new(rmid.clone())))
+            .expect("Parse RMID from reader");
+        assert_eq!(from_io, expected);
+
+        let truncated = &rmid[..16];
+        let error = RawMidi::try_from_midi_stream(IoStream::new(Cursor::new(truncated)))
+            .expect_err("Truncated container fails");
+        assert!(matches!(
+            error,
+            ChunkParseError::Stream {
+                source: StreamError::Io(e),
+                ..
+            } if e.kind() == std::io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn midi_file_reads_any_path_like_value() {
+        let path = std::ffi::OsString::from("test/run.mid");
+        let from_os_string: Vec<_> = MidiFile(path)
+            .get_midi_bytes()
+            .expect("Read from OsString")
+            .collect();
+        let from_str: Vec<_> = "test/run.mid"
+            .get_midi_bytes()
+            .expect("Read from str")
+            .collect();
+
+        assert_eq!(from_os_string, from_str);
+    }
+
+    #[test]
+    fn lazy_chunks_only_decode_requested_tracks() {
+        let mut bytes = std::fs::read("test/run.mid").expect("Read test file");
+        let chunks: Vec<_> = RawMidi::lazy_chunks(&bytes)
+            .collect::<Result<_, _>>()
+            .expect("Walk chunks");
+        let owned = RawMidi::try_from_midi_stream(bytes.clone().into_iter()).expect("Parse");
+        assert_eq!(chunks.len(), owned.chunks.len());
+
+        // Corrupt every track but the first, which is never decoded
+        let first_track = chunks.iter().position(LazyChunk::is_track).unwrap();
+        let later_tracks: Vec<_> = chunks
+            .iter()
+            .skip(first_track + 1)
+            .filter(|c| c.is_track())
+            .map(|c| c.offset + 8)
+            .collect();
+        for offset in later_tracks {
+            bytes[offset] = 0xF4;
+            bytes[offset + 1] = 0xF4;
+        }
+
+        let first = RawMidi::lazy_chunks(&bytes)
+            .nth(first_track)
+            .expect("Track chunk")
+            .expect("Split chunk");
+        let events: Vec<_> = first
+            .track_events()
+            .expect("Is a track")
+            .collect::<Result<_, _>>()
+            .expect("Decode first track");
+        assert_eq!(
+            ParsedChunk::Track(TrackChunk::new(events)),
+            owned.chunks[first_track]
+        );
+
+        let truncated = &bytes[..bytes.len() - 1];
+        let last = RawMidi::lazy_chunks(truncated).last().expect("Last item");
+        assert!(matches!(
+            last,
+            Err(ChunkParseError::Stream {
+                source: StreamError::Truncated { .. },
+                ..
+            })
+        ));
+    }
 }
@@ -0,0 +1,410 @@
+//! RIFF `RMID` containers, a MIDI file wrapped in a RIFF `data` chunk alongside optional `INFO`
+//! metadata and other chunks such as embedded DLS sound banks
+
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{chunk::ChunkParseError, writer::MidiWriteable, Midi, RawMidi};
+
+/// RIFF chunk identifier
+const RIFF: [u8; 4] = *b"RIFF";
+/// Form type of a RIFF MIDI file
+const RMID: [u8; 4] = *b"RMID";
+/// Chunk holding the wrapped MIDI file
+pub(crate) const DATA: [u8; 4] = *b"data";
+/// List chunk identifier
+const LIST: [u8; 4] = *b"LIST";
+/// List type holding metadata
+const INFO: [u8; 4] = *b"INFO";
+
+/// INFO field holding the title
+pub const INFO_TITLE: [u8; 4] = *b"INAM";
+/// INFO field holding the copyright
+pub const INFO_COPYRIGHT: [u8; 4] = *b"ICOP";
+/// INFO field holding comments
+pub const INFO_COMMENTS: [u8; 4] = *b"ICMT";
+
+/// Returns true if the bytes start with a RIFF `RMID` header
+pub fn is_rmid(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && bytes[0..4] == RIFF && bytes[8..12] == RMID
+}
+
+/// Error while reading a RIFF `RMID` container
+#[derive(Debug)]
+pub enum RmidError {
+    /// The bytes don't start with a RIFF `RMID` header
+    NotRmid,
+    /// A chunk's length runs past the end of the data
+    Truncated,
+    /// The container has no `data` chunk
+    MissingData,
+    /// The wrapped MIDI file failed to parse
+    MidiParseError(ChunkParseError),
+}
+
+impl core::error::Error for RmidError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::MidiParseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl core::fmt::Display for RmidError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotRmid => write![f, "Data is not a RIFF RMID container"],
+            Self::Truncated => write![f, "RIFF chunk runs past the end of the data"],
+            Self::MissingData => write![f, "RIFF RMID container has no data chunk"],
+            Self::MidiParseError(_) => write![f, "Failed to parse wrapped MIDI data"],
+        }
+    }
+}
+impl From<ChunkParseError> for RmidError {
+    fn from(f: ChunkParseError) -> Self {
+        Self::MidiParseError(f)
+    }
+}
+
+/// A single field of an `INFO` list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoField {
+    /// 4 character field identifier, such as [`INFO_TITLE`]
+    pub id: [u8; 4],
+    /// The field's text
+    pub value: String,
+}
+
+/// Metadata stored in an `INFO` list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RmidInfo {
+    /// All fields in the order they appear
+    pub fields: Vec<InfoField>,
+}
+
+impl RmidInfo {
+    /// Gets the first field with the given identifier
+    pub fn get(&self, id: [u8; 4]) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.id == id)
+            .map(|field| field.value.as_str())
+    }
+
+    /// Sets a field, replacing an existing field with the same identifier
+    pub fn set(&mut self, id: [u8; 4], value: impl Into<String>) {
+        let value = value.into();
+        match self.fields.iter_mut().find(|field| field.id == id) {
+            Some(field) => field.value = value,
+            None => self.fields.push(InfoField { id, value }),
+        }
+    }
+
+    /// Sets a field, returning the info for chaining
+    pub fn with(mut self, id: [u8; 4], value: impl Into<String>) -> Self {
+        self.set(id, value);
+        self
+    }
+
+    /// Gets the title
+    pub fn title(&self) -> Option<&str> {
+        self.get(INFO_TITLE)
+    }
+
+    /// Gets the copyright
+    pub fn copyright(&self) -> Option<&str> {
+        self.get(INFO_COPYRIGHT)
+    }
+
+    /// Gets the comments
+    pub fn comments(&self) -> Option<&str> {
+        self.get(INFO_COMMENTS)
+    }
+
+    /// Returns true if there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl MidiWriteable for RmidInfo {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        let mut bytes = INFO.to_vec();
+        for field in self.fields {
+            let mut value = field.value.into_bytes();
+            value.push(0);
+            write_riff_chunk(&mut bytes, field.id, &value);
+        }
+
+        bytes
+    }
+}
+
+/// A raw RIFF chunk of a type this crate doesn't interpret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiffChunk {
+    /// 4 character chunk identifier
+    pub id: [u8; 4],
+    /// The chunk's payload, without padding
+    pub data: Vec<u8>,
+}
+
+/// A MIDI file unwrapped from a RIFF `RMID` container
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rmid {
+    /// The raw bytes of the wrapped MIDI file
+    pub data: Vec<u8>,
+    /// Metadata from the `INFO` list
+    pub info: RmidInfo,
+    /// Every other chunk in the container in order, such as an embedded DLS sound bank
+    pub chunks: Vec<RiffChunk>,
+}
+
+impl Rmid {
+    /// Wraps MIDI bytes in a container with the given metadata
+    pub fn new(data: Vec<u8>, info: RmidInfo) -> Self {
+        Self {
+            data,
+            info,
+            chunks: vec![],
+        }
+    }
+
+    /// Reads a RIFF `RMID` container
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, RmidError> {
+        if !is_rmid(bytes) {
+            return Err(RmidError::NotRmid);
+        }
+
+        let riff_len = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+        let end = (8 + riff_len).min(bytes.len());
+
+        let mut data = None;
+        let mut info = RmidInfo::default();
+        let mut chunks = vec![];
+
+        for (id, payload) in RiffChunks::new(&bytes[12..end]) {
+            let payload = payload?;
+            match id {
+                DATA if data.is_none() => data = Some(payload.to_vec()),
+                LIST if payload.starts_with(&INFO) => {
+                    for (id, value) in RiffChunks::new(&payload[4..]) {
+                        let value = value?;
+                        let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+                        info.fields.push(InfoField {
+                            id,
+                            value: String::from_utf8_lossy(&value[..end]).into_owned(),
+                        });
+                    }
+                }
+                id => chunks.push(RiffChunk {
+                    id,
+                    data: payload.to_vec(),
+                }),
+            }
+        }
+
+        Ok(Self {
+            data: data.ok_or(RmidError::MissingData)?,
+            info,
+            chunks,
+        })
+    }
+
+    /// Parses the wrapped MIDI file
+    pub fn to_raw_midi(&self) -> Result<RawMidi, RmidError> {
+        Ok(RawMidi::try_from_midi_stream(self.data.iter().copied())?)
+    }
+}
+
+impl MidiWriteable for Rmid {
+    fn to_midi_bytes(self) -> Vec<u8> {
+        let mut body = RMID.to_vec();
+        write_riff_chunk(&mut body, DATA, &self.data);
+        if !self.info.is_empty() {
+            write_riff_chunk(&mut body, LIST, &self.info.to_midi_bytes());
+        }
+        for chunk in self.chunks {
+            write_riff_chunk(&mut body, chunk.id, &chunk.data);
+        }
+
+        let mut bytes = Vec::with_capacity(body.len() + 8);
+        write_riff_chunk(&mut bytes, RIFF, &body);
+
+        bytes
+    }
+}
+
+impl Midi {
+    /// Wraps the MIDI file in a RIFF `RMID` container with the given metadata
+    pub fn to_rmid_bytes(self, info: RmidInfo) -> Vec<u8> {
+        Rmid::new(self.to_midi_bytes(), info).to_midi_bytes()
+    }
+}
+
+/// Unwraps the MIDI data from a RIFF `RMID` container, or returns the bytes unchanged if they
+/// aren't one. Fails if the bytes start with an `RMID` header but the container is malformed
+pub(crate) fn unwrap_rmid(bytes: Vec<u8>) -> Result<Vec<u8>, RmidError> {
+    if !is_rmid(&bytes) {
+        return Ok(bytes);
+    }
+
+    Ok(Rmid::try_from_bytes(&bytes)?.data)
+}
+
+/// Writes a RIFF chunk with a little endian length, padded to an even length
+fn write_riff_chunk(bytes: &mut Vec<u8>, id: [u8; 4], data: &[u8]) {
+    bytes.extend(id);
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+}
+
+/// Iterator over the RIFF chunks in a span of bytes
+struct RiffChunks<'a> {
+    /// Bytes that haven't been read
+    remaining: &'a [u8],
+}
+
+impl<'a> RiffChunks<'a> {
+    /// Iterates over the chunks in a span of bytes
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { remaining: bytes }
+    }
+}
+
+impl<'a> Iterator for RiffChunks<'a> {
+    type Item = ([u8; 4], Result<&'a [u8], RmidError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.len() < 8 {
+            return None;
+        }
+
+        let id = [
+            self.remaining[0],
+            self.remaining[1],
+            self.remaining[2],
+            self.remaining[3],
+        ];
+        let len = u32::from_le_bytes([
+            self.remaining[4],
+            self.remaining[5],
+            self.remaining[6],
+            self.remaining[7],
+        ]) as usize;
+
+        let rest = &self.remaining[8..];
+        if len > rest.len() {
+            self.remaining = &[];
+            return Some((id, Err(RmidError::Truncated)));
+        }
+
+        let padded = (len + len % 2).min(rest.len());
+        self.remaining = &rest[padded..];
+
+        Some((id, Ok(&rest[..len])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk::track::TrackChunk, reader::MidiReadable, writer::MidiWriteable, Midi, RawMidi,
+    };
+
+    use super::{
+        is_rmid, RiffChunk, Rmid, RmidError, RmidInfo, INFO_COMMENTS, INFO_COPYRIGHT, INFO_TITLE,
+    };
+
+    /// Builds a small MIDI file
+    fn midi() -> Midi {
+        Midi::builder()
+            .track(TrackChunk::builder().build().expect("Build track"))
+            .build()
+            .expect("Build MIDI")
+    }
+
+    #[test]
+    fn rmid_round_trips_with_info() {
+        let info = RmidInfo::default()
+            .with(INFO_TITLE, "Song")
+            .with(INFO_COPYRIGHT, "(c) 2025")
+            .with(INFO_COMMENTS, "Odd");
+        let bytes = midi().to_rmid_bytes(info.clone());
+
+        assert!(is_rmid(&bytes));
+        assert_eq!(bytes.len() % 2, 0);
+
+        let rmid = Rmid::try_from_bytes(&bytes).expect("Read RMID container");
+        assert_eq!(rmid.info, info);
+        assert_eq!(rmid.info.title(), Some("Song"));
+        assert_eq!(rmid.info.comments(), Some("Odd"));
+        assert!(rmid.chunks.is_empty());
+
+        let parsed = rmid
+            .to_raw_midi()
+            .expect("Parse wrapped MIDI")
+            .check_into_midi()
+            .expect("Sanitize MIDI");
+        assert_eq!(parsed, midi());
+    }
+
+    #[test]
+    fn rmid_keeps_other_chunks() {
+        let mut rmid = Rmid::new(midi().to_midi_bytes(), RmidInfo::default());
+        rmid.chunks.push(RiffChunk {
+            id: *b"RIFF",
+            data: b"DLS \x01".to_vec(),
+        });
+
+        let bytes = rmid.clone().to_midi_bytes();
+        assert_eq!(Rmid::try_from_bytes(&bytes).expect("Read RMID"), rmid);
+    }
+
+    #[test]
+    fn midi_readable_unwraps_rmid() {
+        let bytes = midi().to_rmid_bytes(RmidInfo::default().with(INFO_TITLE, "Song"));
+
+        let stream = bytes.get_midi_bytes().expect("Get MIDI bytes");
+        let parsed = RawMidi::try_from_midi_stream(stream)
+            .expect("Parse MIDI stream")
+            .check_into_midi()
+            .expect("Sanitize MIDI");
+        assert_eq!(parsed, midi());
+    }
+
+    #[test]
+    fn malformed_rmid_errors_are_kept() {
+        let bytes = midi().to_rmid_bytes(RmidInfo::default());
+        let truncated = &bytes[..bytes.len() - 4];
+
+        assert!(matches!(
+            truncated.get_midi_bytes().err(),
+            Some(RmidError::Truncated)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn malformed_rmid_files_fail_with_invalid_data() {
+        let bytes = midi().to_rmid_bytes(RmidInfo::default());
+        let truncated = &bytes[..bytes.len() - 4];
+
+        let path = std::env::temp_dir().join(format!(
+            "miami_malformed_rmid_files_fail_with_invalid_data_{}.rmi",
+            std::process::id()
+        ));
+        std::fs::write(&path, truncated).expect("Write RMID file");
+        let error = (&path).get_midi_bytes().err();
+        std::fs::remove_file(&path).expect("Remove RMID file");
+
+        let error = error.expect("Reading a truncated RMID file fails");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(matches!(
+            error.get_ref().and_then(|e| e.downcast_ref()),
+            Some(RmidError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+//! Tempo maps for converting between ticks and wall-clock time
+
+use alloc::{vec, vec::Vec};
+
+use core::time::Duration;
+
+use crate::{
+    chunk::{header::Division, track::meta::MetaEvent, track::Event},
+    Midi,
+};
+
+/// Tempo in effect before the first tempo event, 120 beats per minute
+pub const DEFAULT_TEMPO: u32 = 500_000;
+
+/// Nanoseconds per microsecond
+const NANOS_PER_MICRO: u128 = 1_000;
+/// Nanoseconds per second
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// A single tempo change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TempoChange {
+    /// Absolute tick the tempo takes effect at
+    pub tick: u64,
+    /// Microseconds per quarter note from this tick onwards
+    pub micros_per_quarter: u32,
+}
+
+/// Every tempo change in a MIDI file, used to convert between ticks and wall-clock time.
+///
+/// For [`Division::Metrical`] files, tempo changes from every track are gathered, and the
+/// latest track wins when several change tempo at the same tick. For
+/// [`Division::TimeCodeBased`] files ticks have a fixed length and tempo changes are ignored
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoMap {
+    /// The file's division
+    division: Division,
+    /// Tempo changes sorted by tick, paired with the time in nanoseconds they occur at. Always
+    /// starts with a change at tick 0
+    changes: Vec<(TempoChange, u128)>,
+    /// Absolute tick of the last event in the file
+    end_tick: u64,
+}
+
+impl TempoMap {
+    /// Builds the tempo map of a MIDI file
+    pub fn new(midi: &Midi) -> Self {
+        let mut tempos = vec![];
+        let mut end_tick = 0;
+
+        for track in &midi.tracks {
+            for (tick, event) in track.absolute_events() {
+                end_tick = end_tick.max(tick);
+                if let Event::MetaEvent(MetaEvent::Tempo(tempo)) = event {
+                    tempos.push(TempoChange {
+                        tick,
+                        micros_per_quarter: *tempo,
+                    });
+                }
+            }
+        }
+
+        tempos.sort_by_key(|change| change.tick);
+
+        let mut changes: Vec<TempoChange> = vec![TempoChange {
+            tick: 0,
+            micros_per_quarter: DEFAULT_TEMPO,
+        }];
+
+        for change in tempos {
+            match changes.last_mut() {
+                Some(last) if last.tick == change.tick => *last = change,
+                _ => changes.push(change),
+            }
+        }
+
+        let mut map = Self {
+            division: midi.header.division(),
+            changes: Vec::with_capacity(changes.len()),
+            end_tick,
+        };
+
+        let mut nanos = 0;
+        let mut previous: Option<TempoChange> = None;
+        for change in changes {
+            if let Some(previous) = previous {
+                nanos += map.segment_nanos(previous, change.tick - previous.tick);
+            }
+            map.changes.push((change, nanos));
+            previous = Some(change);
+        }
+
+        map
+    }
+
+    /// Gets every tempo change in order, starting with the tempo in effect at tick 0
+    pub fn changes(&self) -> impl Iterator<Item = TempoChange> + '_ {
+        self.changes.iter().map(|(change, _)| *change)
+    }
+
+    /// Gets the tempo in microseconds per quarter note in effect at a tick
+    pub fn tempo_at(&self, tick: u64) -> u32 {
+        self.changes[self.segment_for_tick(tick)]
+            .0
+            .micros_per_quarter
+    }
+
+    /// Converts an absolute tick to the wall-clock time it occurs at
+    pub fn tick_to_duration(&self, tick: u64) -> Duration {
+        let (change, start) = self.changes[self.segment_for_tick(tick)];
+        let nanos = start + self.segment_nanos(change, tick - change.tick);
+
+        nanos_to_duration(nanos)
+    }
+
+    /// Converts a wall-clock time to the absolute tick that is playing at that time, rounding
+    /// down
+    pub fn duration_to_tick(&self, duration: Duration) -> u64 {
+        let nanos = duration.as_nanos();
+        let index = self
+            .changes
+            .partition_point(|(_, start)| *start <= nanos)
+            .saturating_sub(1);
+        let (change, start) = self.changes[index];
+        let (numerator, denominator) = self.nanos_per_tick(change);
+
+        let ticks = (nanos - start) * denominator / numerator.max(1);
+        change.tick + ticks.min(u64::MAX as u128) as u64
+    }
+
+    /// Gets the absolute tick of the last event in the file
+    pub fn end_tick(&self) -> u64 {
+        self.end_tick
+    }
+
+    /// Gets the total playback length of the file, up to its last event
+    pub fn duration(&self) -> Duration {
+        self.tick_to_duration(self.end_tick)
+    }
+
+    /// Finds the index of the tempo change in effect at a tick
+    fn segment_for_tick(&self, tick: u64) -> usize {
+        self.changes
+            .partition_point(|(change, _)| change.tick <= tick)
+            .saturating_sub(1)
+    }
+
+    /// Gets the length of a tick while a tempo is in effect, as a fraction of nanoseconds
+    fn nanos_per_tick(&self, change: TempoChange) -> (u128, u128) {
+        match self.division {
+            Division::Metrical(ticks_per_quarter) => (
+                change.micros_per_quarter as u128 * NANOS_PER_MICRO,
+                ticks_per_quarter.max(1) as u128,
+            ),
+            Division::TimeCodeBased(smpte) => {
+                let (numerator, denominator) = smpte.ticks_per_second();
+                (
+                    NANOS_PER_SECOND * denominator as u128,
+                    numerator.max(1) as u128,
+                )
+            }
+        }
+    }
+
+    /// Gets the length in nanoseconds of a number of ticks while a tempo is in effect
+    fn segment_nanos(&self, change: TempoChange, ticks: u64) -> u128 {
+        let (numerator, denominator) = self.nanos_per_tick(change);
+        ticks as u128 * numerator / denominator
+    }
+}
+
+impl From<&Midi> for TempoMap {
+    fn from(value: &Midi) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Converts nanoseconds to a duration, saturating at the largest duration
+fn nanos_to_duration(nanos: u128) -> Duration {
+    let secs = nanos / NANOS_PER_SECOND;
+    let subsec = (nanos % NANOS_PER_SECOND) as u32;
+
+    if secs > u64::MAX as u128 {
+        Duration::MAX
+    } else {
+        Duration::new(secs as u64, subsec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use crate::tests::midi_from_tracks;
+
+    use super::{TempoChange, TempoMap};
+
+    #[test]
+    fn default_tempo_is_used_without_tempo_events() {
+        let midi = midi_from_tracks(96, &[&[0x83, 0x00, 0xFF, 0x2F, 0x00]]);
+        let map = TempoMap::new(&midi);
+
+        // 384 ticks at 96 ticks per quarter is 4 quarters at 120 bpm
+        assert_eq!(map.end_tick(), 384);
+        assert_eq!(map.duration(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn tempo_changes_across_tracks_are_merged() {
+        let conductor: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // 1,000,000 us per quarter
+            0x60, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // 500,000 us per quarter at tick 96
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let notes: &[u8] = &[
+            0x81, 0x40, 0x90, 0x3C, 0x40, // Note on at tick 192
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let midi = midi_from_tracks(96, &[conductor, notes]);
+        let map = TempoMap::new(&midi);
+
+        assert_eq!(
+            map.changes().collect::<Vec<_>>(),
+            vec![
+                TempoChange {
+                    tick: 0,
+                    micros_per_quarter: 1_000_000
+                },
+                TempoChange {
+                    tick: 96,
+                    micros_per_quarter: 500_000
+                },
+            ]
+        );
+
+        assert_eq!(map.tick_to_duration(48), Duration::from_millis(500));
+        assert_eq!(map.tick_to_duration(96), Duration::from_secs(1));
+        assert_eq!(map.tick_to_duration(192), Duration::from_millis(1500));
+        assert_eq!(map.duration(), Duration::from_millis(1500));
+
+        assert_eq!(map.duration_to_tick(Duration::from_millis(500)), 48);
+        assert_eq!(map.duration_to_tick(Duration::from_millis(1250)), 144);
+    }
+
+    #[test]
+    fn time_code_division_ignores_tempo() {
+        // 25 frames per second, 40 ticks per frame, 1000 ticks per second
+        let division = 0xE728;
+        let track: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, //
+            0x87, 0x68, 0xFF, 0x2F, 0x00, // End of track at tick 1000
+        ];
+        let midi = midi_from_tracks(division, &[track]);
+        let map = TempoMap::new(&midi);
+
+        assert_eq!(map.duration(), Duration::from_secs(1));
+        assert_eq!(map.duration_to_tick(Duration::from_millis(250)), 250);
+    }
+}
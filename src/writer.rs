@@ -4,7 +4,15 @@
 //! into the canonical MIDI byte format. This is particularly useful when you have manipulated
 //! or inspected MIDI data in your application and need to write it back to a file or stream.
 
-use crate::Chunk;
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{
+    io::{self, Write},
+    Chunk,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// A trait for types that can be encoded as MIDI-format bytes.
 ///
@@ -15,6 +23,153 @@ use crate::Chunk;
 pub trait MidiWriteable {
     /// Converts the data to a MIDI format byte sequence
     fn to_midi_bytes(self) -> Vec<u8>;
+
+    /// Converts the data to a MIDI format byte sequence using the provided [`WriteOptions`].
+    /// Types with no configurable output fall back to [`MidiWriteable::to_midi_bytes`]
+    fn to_midi_bytes_with_options(self, _options: WriteOptions) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        self.to_midi_bytes()
+    }
+}
+
+/// A trait for types that can be serialized as MIDI-format bytes from a reference, straight into
+/// any [`std::io::Write`], or the minimal [`crate::io::Write`] without the `std` feature.
+///
+/// Unlike [`MidiWriteable`], nothing is consumed or cloned and no intermediate buffers are built:
+/// chunk lengths are computed ahead of time with a counting pass, so files of any size are written
+/// with constant extra memory and the writer doesn't need to support seeking.
+pub trait WriteMidi {
+    /// Writes the data in MIDI format using the provided [`WriteOptions`]
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> io::Result<()>;
+
+    /// Gets the number of bytes [`WriteMidi::write_midi`] writes with the provided options,
+    /// without buffering them
+    fn midi_len(&self, options: WriteOptions) -> usize {
+        let mut counter = ByteCounter(0);
+        // Counting never fails
+        let _ = self.write_midi(&mut counter, options);
+        counter.0
+    }
+
+    /// Writes the data in MIDI format into a new buffer
+    fn to_midi_vec(&self, options: WriteOptions) -> Vec<u8> {
+        let mut bytes = vec![];
+        // Writing to a `Vec` never fails
+        let _ = self.write_midi(&mut bytes, options);
+        bytes
+    }
+}
+
+/// Writer that discards its input, counting the bytes written
+struct ByteCounter(usize);
+
+#[cfg(feature = "std")]
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for ByteCounter {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0 += buf.len();
+        Ok(())
+    }
+}
+
+/// Writes a value as a variable length quantity
+pub(crate) fn write_vlq<W: Write + ?Sized>(writer: &mut W, value: u32) -> io::Result<()> {
+    let mut bytes = [0; 5];
+    let mut start = bytes.len();
+    let mut value = value;
+
+    loop {
+        start -= 1;
+        bytes[start] = (value & 0x7F) as u8;
+        if start != bytes.len() - 1 {
+            bytes[start] |= 0x80;
+        }
+
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+
+    writer.write_all(&bytes[start..])
+}
+
+/// Writes a chunk header for a chunk body, computing its length ahead of time
+pub(crate) fn write_chunk<W, BODY>(
+    writer: &mut W,
+    chunk_type: [char; 4],
+    body: &BODY,
+    options: WriteOptions,
+) -> io::Result<()>
+where
+    W: Write + ?Sized,
+    BODY: WriteMidi + ?Sized,
+{
+    let chunk = Chunk {
+        chunk_type,
+        length: body.midi_len(options) as u32,
+    };
+    writer.write_all(&chunk.to_midi_bytes())?;
+    body.write_midi(writer, options)
+}
+
+impl WriteMidi for [u8] {
+    fn write_midi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        _options: WriteOptions,
+    ) -> io::Result<()> {
+        writer.write_all(self)
+    }
+}
+
+/// Options controlling how MIDI data is serialized. The default options write every event with
+/// its full status byte, exactly as [`MidiWriteable::to_midi_bytes`] does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WriteOptions {
+    /// Omit status bytes that repeat the previous MIDI event's status (running status)
+    pub running_status: bool,
+    /// Rewrite `NoteOff` events as `NoteOn` events with a velocity of 0 so they can share a
+    /// running status with surrounding `NoteOn` events. Release velocities are lost. Only takes
+    /// effect when `running_status` is enabled
+    pub note_off_as_note_on: bool,
+}
+
+impl WriteOptions {
+    /// Options that use running status, omitting repeated status bytes
+    pub fn running_status() -> Self {
+        Self {
+            running_status: true,
+            note_off_as_note_on: false,
+        }
+    }
+
+    /// Options that use running status and rewrite `NoteOff` as `NoteOn` with velocity 0 for
+    /// maximum compaction
+    pub fn compact() -> Self {
+        Self {
+            running_status: true,
+            note_off_as_note_on: true,
+        }
+    }
 }
 
 impl MidiWriteable for u8 {
@@ -89,21 +244,23 @@ impl MidiWriteable for String {
 #[cfg(test)]
 mod tests {
     use crate::{
-        chunk::ParsedChunk,
+        chunk::{track::TrackChunk, ParsedChunk},
         reader::{MidiReadable, MidiStream},
-        Chunk,
+        Chunk, RawMidi,
     };
 
-    use super::MidiWriteable;
+    use super::{MidiWriteable, WriteMidi, WriteOptions};
 
     #[test]
     fn header_chunk_saves_as_proper_bytes() {
-        let mut stream = "test/test.mid"
+        let mut stream = std::fs::read("test/test.mid")
+            .expect("Read test file")
             .get_midi_bytes()
             .expect("Get MIDI bytes from source");
         let expected = stream
             .read_chunk_data_pair()
-            .map(|val| ParsedChunk::try_from(val))
+            .expect("Read chunk")
+            .map(ParsedChunk::try_from)
             .unwrap()
             .unwrap();
 
@@ -113,10 +270,108 @@ mod tests {
         let mut new_stream = bytes.into_iter();
         let new_header = new_stream
             .read_chunk_data_pair()
-            .map(|val| ParsedChunk::try_from(val))
+            .expect("Read chunk")
+            .map(ParsedChunk::try_from)
             .unwrap()
             .unwrap();
 
         assert_eq!(expected, new_header)
     }
+
+    #[test]
+    fn running_status_output_is_smaller_and_parses_back() {
+        let payload = vec![
+            0x00, 0x90, 0x3C, 0x40, // Note on
+            0x10, 0x90, 0x3E, 0x40, // Note on
+            0x10, 0x80, 0x3C, 0x40, // Note off
+            0x00, 0x80, 0x3E, 0x40, // Note off
+            0x00, 0xFF, 0x2F, 0x00, // End of track
+        ];
+        let track = TrackChunk::try_from(payload.clone()).expect("Parse track payload");
+
+        let compact = track
+            .clone()
+            .to_midi_bytes_with_options(WriteOptions::running_status());
+        assert_eq!(
+            compact,
+            vec![
+                0x00, 0x90, 0x3C, 0x40, //
+                0x10, 0x3E, 0x40, //
+                0x10, 0x80, 0x3C, 0x40, //
+                0x00, 0x3E, 0x40, //
+                0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+
+        let reparsed = TrackChunk::try_from(compact).expect("Parse compacted track");
+        assert_eq!(reparsed, track);
+        assert_eq!(track.to_midi_bytes(), payload)
+    }
+
+    #[test]
+    fn compact_output_rewrites_note_off() {
+        let payload = vec![
+            0x00, 0x90, 0x3C, 0x40, // Note on
+            0x10, 0x80, 0x3C, 0x40, // Note off
+            0x00, 0xFF, 0x2F, 0x00, // End of track
+        ];
+        let track = TrackChunk::try_from(payload).expect("Parse track payload");
+
+        let compact = track.to_midi_bytes_with_options(WriteOptions::compact());
+        assert_eq!(
+            compact,
+            vec![0x00, 0x90, 0x3C, 0x40, 0x10, 0x3C, 0x00, 0x00, 0xFF, 0x2F, 0x00]
+        )
+    }
+
+    #[test]
+    fn default_options_match_plain_output() {
+        let midi = RawMidi::try_from_midi_stream(
+            std::fs::read("test/test.mid")
+                .expect("Read test file")
+                .get_midi_bytes()
+                .expect("Get MIDI bytes from source"),
+        )
+        .expect("Parse data as a MIDI stream");
+
+        assert_eq!(
+            midi.clone().to_midi_bytes(),
+            midi.to_midi_bytes_with_options(WriteOptions::default())
+        )
+    }
+
+    #[test]
+    fn streaming_writer_matches_owned_output() {
+        let midi = RawMidi::try_from_midi_stream(
+            std::fs::read("test/test.mid")
+                .expect("Read test file")
+                .get_midi_bytes()
+                .expect("Get MIDI bytes from source"),
+        )
+        .expect("Parse MIDI")
+        .check_into_midi()
+        .expect("Sanitize MIDI");
+
+        for options in [WriteOptions::default(), WriteOptions::compact()] {
+            let mut written = vec![];
+            midi.write_midi(&mut written, options).expect("Write MIDI");
+
+            assert_eq!(written.len(), midi.midi_len(options));
+            assert_eq!(written, midi.clone().to_midi_bytes_with_options(options));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn streaming_writer_surfaces_io_errors() {
+        let midi = crate::tests::midi_from_tracks(96, &[&[0x00, 0xFF, 0x2F, 0x00]]);
+
+        let mut buffer = [0u8; 16];
+        let mut short = std::io::Cursor::new(&mut buffer[..]);
+        let error = midi
+            .write_midi(&mut short, WriteOptions::default())
+            .expect_err("Buffer is too small");
+
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero)
+    }
 }